@@ -1,9 +1,16 @@
 use crate::app_modes;
+use crate::app_modes::viewport::Viewport;
+use crate::app_modes::AppMode;
 use crate::config::TermvizConfig;
+use crate::diagnostics::{DiagnosticsListener, Level};
 use crate::footprint::get_footprint;
 use crate::listeners::Listeners;
+use crate::player::Player;
+use crate::rosout::LogListener;
+use crate::source;
+use crate::tf::TfListener;
 use crossterm::{
-    event::EnableMouseCapture,
+    event::{EnableMouseCapture, KeyCode},
     execute,
     terminal::{enable_raw_mode, size, EnterAlternateScreen},
 };
@@ -15,7 +22,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 use tui::backend::Backend;
 use tui::backend::CrosstermBackend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Row, Table, Wrap};
@@ -26,10 +33,18 @@ pub struct App<B: Backend> {
     show_help: bool,
     keymap: HashMap<String, String>,
     app_modes: Vec<Box<dyn app_modes::BaseMode<B>>>,
+    log_listener: Arc<LogListener>,
+    diagnostics_listener: Arc<DiagnosticsListener>,
+    player: Option<Rc<RefCell<Player>>>,
+    viewport: Rc<RefCell<Viewport>>,
 }
 
 impl<B: Backend> App<B> {
-    pub fn new(tf_listener: Arc<rustros_tf::TfListener>, config: TermvizConfig) -> App<B> {
+    pub fn new(
+        tf_listener: Arc<TfListener>,
+        config: TermvizConfig,
+        player: Option<Player>,
+    ) -> App<B> {
         let config_copy = config.clone();
         let listeners = Listeners::new(
             tf_listener.clone(),
@@ -44,7 +59,7 @@ impl<B: Backend> App<B> {
             config.polygon_stamped_topics,
             config.path_topics,
         );
-        let viewport = Rc::new(RefCell::new(app_modes::viewport::Viewport::new(
+        let viewport = Rc::new(RefCell::new(Viewport::new(
             &config.fixed_frame,
             &config.robot_frame,
             tf_listener,
@@ -52,24 +67,87 @@ impl<B: Backend> App<B> {
             &get_footprint(),
             config.axis_length,
             config.zoom_factor,
+            &config.view_bookmarks,
+            &config.image_topics,
+            &config.hud_topics,
             listeners,
             size().unwrap(),
         )));
-        let send_pose = Box::new(app_modes::send_pose::SendPose::new(
-            &config.send_pose_topics,
+        let player = player.map(|player| Rc::new(RefCell::new(player)));
+        let log_listener = Arc::new(LogListener::new(&config.rosout));
+        let diagnostics_listener = Arc::new(DiagnosticsListener::new(&config.diagnostics));
+        let image_view = Box::new(app_modes::image_view::ImageView::new(
+            config.image_topics.clone(),
             viewport.clone(),
         ));
-        let teleop = Box::new(app_modes::teleoperate::Teleoperate::new(
-            viewport,
-            config.teleop,
+        let logs = Box::new(app_modes::logs::Logs::new(log_listener.clone()));
+        let diagnostics = Box::new(app_modes::diagnostics::Diagnostics::new(
+            diagnostics_listener.clone(),
         ));
-        let topic_manager = Box::new(app_modes::topic_managment::TopicManager::new(config_copy));
-        let image_view = Box::new(app_modes::image_view::ImageView::new(config.image_topics));
+        let app_modes: Vec<Box<dyn app_modes::BaseMode<B>>> = if source::is_offline() {
+            // Only the modes that do not need a ROS connection
+            let map_view = Box::new(app_modes::map_view::MapView::new(viewport.clone()));
+            let mut offline_modes: Vec<Box<dyn app_modes::BaseMode<B>>> =
+                vec![map_view, image_view, logs, diagnostics];
+            if let Some(player) = &player {
+                offline_modes.insert(
+                    0,
+                    Box::new(app_modes::playback::Playback::new(
+                        viewport.clone(),
+                        player.clone(),
+                    )),
+                );
+            }
+            offline_modes
+        } else {
+            let send_pose = Box::new(app_modes::send_pose::SendPose::new(
+                &config.send_pose_topics,
+                viewport.clone(),
+            ));
+            let send_goal = Box::new(app_modes::send_goal::SendGoal::new(
+                &config.goal_actions,
+                viewport.clone(),
+            ));
+            let waypoints = Box::new(app_modes::waypoints::Waypoints::new(
+                &config.waypoints,
+                viewport.clone(),
+            ));
+            let teleop = Box::new(app_modes::teleoperate::Teleoperate::new(
+                viewport.clone(),
+                config.teleop,
+            ));
+            let topic_manager =
+                Box::new(app_modes::topic_managment::TopicManager::new(config_copy));
+            let topic_echo = Box::new(app_modes::topic_echo::TopicEcho::new());
+            let plot = Box::new(app_modes::plot::Plot::new(config.plots));
+            let parameters = Box::new(app_modes::parameters::Parameters::new());
+            let service_caller = Box::new(app_modes::service_caller::ServiceCaller::new(
+                config.favourite_services,
+            ));
+            vec![
+                send_pose,
+                teleop,
+                image_view,
+                topic_manager,
+                send_goal,
+                waypoints,
+                topic_echo,
+                plot,
+                parameters,
+                service_caller,
+                logs,
+                diagnostics,
+            ]
+        };
         App {
             mode: 1,
             show_help: false,
             keymap: config.key_mapping,
-            app_modes: vec![send_pose, teleop, image_view, topic_manager],
+            app_modes,
+            log_listener,
+            diagnostics_listener,
+            player,
+            viewport,
         }
     }
 
@@ -83,6 +161,10 @@ impl<B: Backend> App<B> {
     }
 
     pub fn run(&mut self) {
+        // The playback continues in all the modes
+        if let Some(player) = &self.player {
+            player.borrow_mut().update();
+        }
         self.app_modes[self.mode - 1].run();
     }
 
@@ -91,9 +173,60 @@ impl<B: Backend> App<B> {
             self.show_help(f);
         } else {
             self.app_modes[self.mode - 1].draw(f);
+            self.draw_badges(f);
         }
     }
 
+    /// Draws a summary of the robot state in the top right corner, visible in all the modes.
+    fn draw_badges(&self, f: &mut Frame<B>) {
+        let mut spans = Vec::new();
+        let summary = self.diagnostics_listener.summary();
+        if summary.iter().sum::<usize>() > 0 {
+            // Worst level and number of statuses not OK, e.g. "DIAG 2W 1E"
+            let mut text = " DIAG".to_string();
+            let mut worst = Level::Ok;
+            for (level, count) in Level::ALL.iter().zip(summary.iter()).skip(1) {
+                if *count > 0 {
+                    text += &format!(" {}{}", count, &level.name()[..1]);
+                    worst = *level;
+                }
+            }
+            if worst == Level::Ok {
+                text += " OK";
+            }
+            let color = match worst {
+                Level::Ok => Color::Green,
+                Level::Warn => Color::Yellow,
+                Level::Error => Color::Red,
+                Level::Stale => Color::DarkGray,
+            };
+            spans.push(Span::styled(
+                text + " ",
+                Style::default().fg(Color::Black).bg(color),
+            ));
+        }
+        let counts = self.log_listener.counts();
+        if counts.warnings > 0 {
+            spans.push(Span::styled(
+                format!(" W:{} ", counts.warnings),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+        }
+        if counts.errors > 0 {
+            spans.push(Span::styled(
+                format!(" E:{} ", counts.errors),
+                Style::default().fg(Color::White).bg(Color::Red),
+            ));
+        }
+        if spans.is_empty() {
+            return;
+        }
+        let area = f.size();
+        let width = (spans.iter().map(|span| span.width()).sum::<usize>() as u16).min(area.width);
+        let badge_area = Rect::new(area.x + area.width - width, area.y, width, 1);
+        f.render_widget(Paragraph::new(Spans::from(spans)), badge_area);
+    }
+
     pub fn handle_input(&mut self, input: &String) {
         if input == app_modes::input::SHOW_HELP {
             if !self.show_help {
@@ -106,7 +239,8 @@ impl<B: Backend> App<B> {
         let mut maybe_new_mode = None;
         match input.trim().parse::<usize>() {
             Ok(mode) => {
-                maybe_new_mode = Some(mode);
+                // 0 is the key after 9
+                maybe_new_mode = Some(if mode == 0 { 10 } else { mode });
             }
             Err(_e) => match input.as_str() {
                 app_modes::input::MODE_1 => maybe_new_mode = Some(1),
@@ -118,6 +252,13 @@ impl<B: Backend> App<B> {
                 app_modes::input::MODE_7 => maybe_new_mode = Some(7),
                 app_modes::input::MODE_8 => maybe_new_mode = Some(8),
                 app_modes::input::MODE_9 => maybe_new_mode = Some(9),
+                app_modes::input::NEXT_MODE => {
+                    maybe_new_mode = Some(self.mode % self.app_modes.len() + 1)
+                }
+                app_modes::input::PREVIOUS_MODE => {
+                    maybe_new_mode =
+                        Some((self.mode + self.app_modes.len() - 2) % self.app_modes.len() + 1)
+                }
                 _ => {}
             },
         }
@@ -138,6 +279,20 @@ impl<B: Backend> App<B> {
         self.app_modes[self.mode - 1].handle_input(input);
     }
 
+    pub fn is_editing(&self) -> bool {
+        // The view picker of the viewport can only be opened from the modes using it
+        !self.show_help
+            && (self.viewport.borrow().is_editing() || self.app_modes[self.mode - 1].is_editing())
+    }
+
+    pub fn handle_text_input(&mut self, key: &KeyCode) {
+        if self.viewport.borrow().is_editing() {
+            self.viewport.borrow_mut().handle_text_input(key);
+        } else {
+            self.app_modes[self.mode - 1].handle_text_input(key);
+        }
+    }
+
     pub fn show_help(&self, f: &mut Frame<B>)
     where
         B: Backend,
@@ -162,6 +317,14 @@ impl<B: Backend> App<B> {
                 app_modes::input::SHOW_HELP.to_string(),
                 "Opens/closes this page.".to_string(),
             ],
+            [
+                app_modes::input::NEXT_MODE.to_string(),
+                "Switches to the next mode.".to_string(),
+            ],
+            [
+                app_modes::input::PREVIOUS_MODE.to_string(),
+                "Switches to the previous mode.".to_string(),
+            ],
             ["Ctrl+c".to_string(), "Quits the application.".to_string()],
         ]);
         for e in &mut key_bindings_raw {
@@ -171,10 +334,16 @@ impl<B: Backend> App<B> {
             }
         }
         for i in 0..self.app_modes.len() {
+            // The modes after the 10th can only be reached with the next and previous mode keys
+            let digit = match i {
+                0..=8 => (i + 1).to_string(),
+                9 => "0".to_string(),
+                _ => "".to_string(),
+            };
             if key_bindings_raw[i][0].contains("Switch") {
-                key_bindings_raw[i][0] = (i + 1).to_string();
+                key_bindings_raw[i][0] = digit;
             } else {
-                key_bindings_raw[i][0] = (i + 1).to_string() + ", " + &key_bindings_raw[i][0];
+                key_bindings_raw[i][0] = digit + ", " + &key_bindings_raw[i][0];
             }
         }
         let title_text = vec![Spans::from(Span::styled(
@@ -0,0 +1,338 @@
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::diagnostics::{parent, DiagnosticsListener, Level, StatusEntry};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use tui::Frame;
+
+pub fn level_style(level: Level) -> Style {
+    match level {
+        Level::Ok => Style::default().fg(Color::Green),
+        Level::Warn => Style::default().fg(Color::Yellow),
+        Level::Error => Style::default().fg(Color::Red),
+        Level::Stale => Style::default().fg(Color::DarkGray),
+    }
+}
+
+/// A line of the diagnostics tree, either a status or one of its key-value pairs.
+struct TreeLine {
+    path: String,
+    depth: usize,
+    text: String,
+    level: Option<Level>,
+    expandable: bool,
+    expanded: bool,
+}
+
+/// Groups and statuses of the tree, with the level of each group being the one published by
+/// the aggregator or the worst level of its children.
+struct Tree<'a> {
+    statuses: &'a BTreeMap<String, StatusEntry>,
+    children: HashMap<String, BTreeSet<String>>,
+    levels: HashMap<String, Level>,
+}
+
+impl<'a> Tree<'a> {
+    fn new(statuses: &'a BTreeMap<String, StatusEntry>) -> Tree<'a> {
+        let mut children: HashMap<String, BTreeSet<String>> = HashMap::new();
+        let mut levels: HashMap<String, Level> = HashMap::new();
+        for (name, status) in statuses.iter() {
+            if name.is_empty() {
+                continue;
+            }
+            let mut path = name.as_str();
+            loop {
+                let level = match statuses.get(path) {
+                    Some(entry) => entry.level,
+                    None => match levels.get(path) {
+                        Some(level) if *level > status.level => *level,
+                        _ => status.level,
+                    },
+                };
+                levels.insert(path.to_string(), level);
+                let parent_path = parent(path);
+                children
+                    .entry(parent_path.to_string())
+                    .or_default()
+                    .insert(path.to_string());
+                if parent_path.is_empty() {
+                    break;
+                }
+                path = parent_path;
+            }
+        }
+        Tree {
+            statuses,
+            children,
+            levels,
+        }
+    }
+
+    /// Flattens the subtree of a path into the lines to display, skipping the children of
+    /// collapsed nodes. Groups are expanded by default, key-value pairs are collapsed.
+    fn flatten(
+        &self,
+        path: &str,
+        depth: usize,
+        toggled: &HashSet<String>,
+        lines: &mut Vec<TreeLine>,
+    ) {
+        let children = self.children.get(path);
+        let status = self.statuses.get(path);
+        let has_values =
+            status.map_or(false, |s| !s.values.is_empty() || !s.hardware_id.is_empty());
+        let expandable = children.is_some() || has_values;
+        let expanded = expandable && (children.is_some() != toggled.contains(path));
+        let name = path[parent(path).len()..].trim_start_matches('/');
+        let text = match status {
+            Some(status) if !status.message.is_empty() => format!("{}: {}", name, status.message),
+            _ => name.to_string(),
+        };
+        lines.push(TreeLine {
+            path: path.to_string(),
+            depth,
+            text,
+            level: self.levels.get(path).copied(),
+            expandable,
+            expanded,
+        });
+        if !expanded {
+            return;
+        }
+        if let Some(status) = status {
+            if !status.hardware_id.is_empty() {
+                lines.push(TreeLine {
+                    path: format!("{}:hardware_id", path),
+                    depth: depth + 1,
+                    text: format!("Hardware ID: {}", status.hardware_id),
+                    level: None,
+                    expandable: false,
+                    expanded: false,
+                });
+            }
+            for (key, value) in status.values.iter() {
+                lines.push(TreeLine {
+                    path: format!("{}:{}", path, key),
+                    depth: depth + 1,
+                    text: format!("{}: {}", key, value),
+                    level: None,
+                    expandable: false,
+                    expanded: false,
+                });
+            }
+        }
+        if let Some(children) = children {
+            for child in children.iter() {
+                self.flatten(child, depth + 1, toggled, lines);
+            }
+        }
+    }
+}
+
+pub struct Diagnostics {
+    listener: Arc<DiagnosticsListener>,
+    toggled: HashSet<String>,
+    lines: Vec<TreeLine>,
+    state: ListState,
+}
+
+impl Diagnostics {
+    pub fn new(listener: Arc<DiagnosticsListener>) -> Diagnostics {
+        Diagnostics {
+            listener,
+            toggled: HashSet::new(),
+            lines: Vec::new(),
+            state: ListState::default(),
+        }
+    }
+
+    fn update_lines(&mut self) {
+        self.lines.clear();
+        {
+            let statuses = self.listener.statuses.read().unwrap();
+            let tree = Tree::new(&statuses);
+            if let Some(roots) = tree.children.get("") {
+                for root in roots.iter() {
+                    tree.flatten(root, 0, &self.toggled, &mut self.lines);
+                }
+            }
+        }
+        let selected = match self.state.selected() {
+            _ if self.lines.is_empty() => None,
+            Some(i) => Some(i.min(self.lines.len() - 1)),
+            None => Some(0),
+        };
+        self.state.select(selected);
+    }
+
+    fn toggle_selected(&mut self) {
+        let line = match self.state.selected().and_then(|i| self.lines.get(i)) {
+            Some(line) if line.expandable => line,
+            _ => return,
+        };
+        let path = line.path.clone();
+        if !self.toggled.remove(&path) {
+            self.toggled.insert(path);
+        }
+        self.update_lines();
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        match self.state.selected().and_then(|i| self.lines.get(i)) {
+            Some(line) if line.expandable && line.expanded != expanded => self.toggle_selected(),
+            _ => (),
+        }
+    }
+
+    fn select(&mut self, offset: i64) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => (i as i64 + offset).rem_euclid(self.lines.len() as i64) as usize,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    fn header(&self) -> Spans {
+        if self.lines.is_empty() {
+            return Spans::from(Span::raw(format!(
+                "No diagnostics received on {}",
+                self.listener.topic
+            )));
+        }
+        let summary = self.listener.summary();
+        let mut spans = Vec::new();
+        for (level, count) in Level::ALL.iter().zip(summary.iter()) {
+            if !spans.is_empty() {
+                spans.push(Span::raw(", "));
+            }
+            spans.push(Span::styled(
+                format!("{}: {}", level.name(), count),
+                level_style(*level),
+            ));
+        }
+        Spans::from(spans)
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Diagnostics {}
+
+impl AppMode for Diagnostics {
+    fn run(&mut self) {
+        self.update_lines();
+    }
+
+    fn reset(&mut self) {}
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Diagnostics shows the status of the diagnostics as a tree grouped by name."
+                .to_string(),
+            "The statuses can be expanded to show their key-value pairs.".to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::UP => self.select(-1),
+            input::DOWN => self.select(1),
+            input::CONFIRM => self.toggle_selected(),
+            input::RIGHT => self.set_expanded(true),
+            input::LEFT => self.set_expanded(false),
+            _ => (),
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Selects the previous line".to_string(),
+            ],
+            [input::DOWN.to_string(), "Selects the next line".to_string()],
+            [
+                input::CONFIRM.to_string(),
+                "Expands/collapses the selected group or status".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Expands the selected group or status".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Collapses the selected group or status".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Diagnostics".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for Diagnostics {
+    fn draw(&self, f: &mut Frame<B>) {
+        let title_text = vec![Spans::from(Span::styled(
+            "Diagnostics",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .horizontal_margin(20)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(2),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = Paragraph::new(self.header())
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let items: Vec<ListItem> = self
+            .lines
+            .iter()
+            .map(|line| {
+                let marker = match (line.expandable, line.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    _ => "  ",
+                };
+                let indent = format!("{}{}", "  ".repeat(line.depth), marker);
+                let spans = match line.level {
+                    Some(level) => Spans::from(vec![
+                        Span::raw(indent),
+                        Span::styled(format!("[{}] ", level.name()), level_style(level)),
+                        Span::raw(line.text.clone()),
+                    ]),
+                    None => Spans::from(vec![
+                        Span::raw(indent),
+                        Span::styled(line.text.clone(), Style::default().fg(Color::Gray)),
+                    ]),
+                };
+                ListItem::new(spans)
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().title("Status").borders(Borders::ALL))
+            .highlight_symbol(">> ");
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+        f.render_stateful_widget(list, areas[2], &mut self.state.clone());
+    }
+}
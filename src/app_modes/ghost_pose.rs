@@ -0,0 +1,148 @@
+//! A ghost pose is a preview outline of the robot that can be moved around the map.
+//! Modes that need the user to select a pose on the map use it.
+
+use crate::app_modes::input;
+use crate::app_modes::viewport::Viewport;
+use crate::transformation;
+use approx::AbsDiffEq;
+use nalgebra::{Isometry2, Vector2};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::style::Color;
+use tui::widgets::canvas::Context;
+
+pub struct GhostPose {
+    viewport: Rc<RefCell<Viewport>>,
+    pub increment: f64,
+    pub robot_pose: Isometry2<f64>,
+    pub pose: Isometry2<f64>,
+    pub active: bool,
+}
+
+impl GhostPose {
+    pub fn new(viewport: Rc<RefCell<Viewport>>) -> GhostPose {
+        let mut ghost = GhostPose {
+            viewport: viewport,
+            increment: 0.1,
+            robot_pose: Isometry2::identity(),
+            pose: Isometry2::identity(),
+            active: false,
+        };
+        ghost.update();
+        ghost
+    }
+
+    /// Updates the robot pose. If the ghost is not active, it follows the robot.
+    pub fn update(&mut self) {
+        let base_link_pose = self.viewport.borrow().tf_listener.lookup_transform(
+            &self.viewport.borrow().static_frame,
+            &self.viewport.borrow().robot_frame,
+            rosrust::Time::new(),
+        );
+
+        self.robot_pose = if base_link_pose.is_ok() {
+            transformation::ros_to_iso2d(&base_link_pose.unwrap().transform)
+        } else {
+            Isometry2::identity()
+        };
+
+        if !self.active {
+            self.pose = self.robot_pose.clone();
+        }
+    }
+
+    /// Moves the ghost back onto the robot.
+    pub fn reset(&mut self) {
+        self.active = false;
+        self.update();
+    }
+
+    /// Moves the ghost relatively to its current pose.
+    pub fn move_pose(&mut self, x: f64, y: f64, yaw: f64) {
+        let new_yaw = self.pose.rotation.angle() + yaw;
+        let new_x = x * new_yaw.cos() - y * new_yaw.sin() + self.pose.translation.x;
+        let new_y = x * new_yaw.sin() + y * new_yaw.cos() + self.pose.translation.y;
+        self.set_pose(Isometry2::new(Vector2::new(new_x, new_y), new_yaw));
+    }
+
+    /// Places the ghost at the given pose in the static frame.
+    pub fn set_pose(&mut self, pose: Isometry2<f64>) {
+        self.pose = pose;
+        self.active = true;
+    }
+
+    /// Returns true if the ghost has been moved away from the robot.
+    pub fn is_set(&self) -> bool {
+        self.pose.abs_diff_ne(&self.robot_pose, 0.01)
+    }
+
+    /// Handles the inputs that move the ghost or change the step size.
+    /// Returns true if the input was consumed.
+    pub fn handle_input(&mut self, input: &String) -> bool {
+        match input.as_str() {
+            input::UP => self.move_pose(self.increment, 0.0, 0.0),
+            input::DOWN => self.move_pose(-self.increment, 0.0, 0.0),
+            input::LEFT => self.move_pose(0.0, self.increment, 0.0),
+            input::RIGHT => self.move_pose(0.0, -self.increment, 0.0),
+            input::ROTATE_LEFT => self.move_pose(0.0, 0.0, self.increment),
+            input::ROTATE_RIGHT => self.move_pose(0.0, 0.0, -self.increment),
+            input::INCREMENT_STEP => self.increment += 0.1,
+            input::DECREMENT_STEP => self.increment -= 0.1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the ghost if it has been moved away from the robot.
+    pub fn draw(&self, ctx: &mut Context) {
+        if self.is_set() {
+            self.viewport
+                .borrow()
+                .draw_robot_outline(ctx, &self.pose, Color::Gray);
+        }
+    }
+
+    /// Returns the horizontal and vertical bounds of a view centered on the ghost.
+    pub fn bounds(&self) -> ([f64; 2], [f64; 2]) {
+        self.viewport
+            .borrow()
+            .bounds_around((self.pose.translation.x, self.pose.translation.y))
+    }
+
+    pub fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Shifts the desired pose positively along the x axis.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Shifts the desired pose negatively along the x axis.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Shifts the desired pose positively along the y axis.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Shifts the desired pose negatively along the y axis.".to_string(),
+            ],
+            [
+                input::ROTATE_LEFT.to_string(),
+                "Rotates the desired pose counter-clockwise.".to_string(),
+            ],
+            [
+                input::ROTATE_RIGHT.to_string(),
+                "Rotates the desired pose clockwise.".to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Increases the step size for manipulating the desired pose.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Decreases the step size for manipulating the desired pose.".to_string(),
+            ],
+        ]
+    }
+}
@@ -1,39 +1,345 @@
 //! Image view mode allows to visualize images from the given topics.
 
 use self::image::ImageListener;
+use crate::app_modes::viewport::Viewport;
 use crate::app_modes::{input, AppMode, BaseMode, Drawable};
 use crate::config::ImageListenerConfig;
 use crate::image;
+use crate::listeners::Listeners;
+use crate::transformation;
+use ::image::{Rgba, RgbaImage};
+use nalgebra::geometry::Point3;
+use rosrust;
+use rosrust_msg::sensor_msgs::CameraInfo;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tui::backend::Backend;
-use tui::layout::{Alignment, Constraint, Layout};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Wrap};
 use tui::Frame;
 use tui_image::{ColorMode, Image};
 pub struct ImageView {
+    viewport: Rc<RefCell<Viewport>>,
     images: Vec<ImageListener>,
     active_sub: usize,
+    maximized: bool,
+    overlay: bool,
+    paused: bool,
+    cursor: (u32, u32),
+    cursor_step: u32,
+    message: String,
+}
+
+/// Initial distance in pixels by which the cursor is moved.
+const DEFAULT_CURSOR_STEP: u32 = 8;
+
+/// Radius of the projected points, relative to the width of the image.
+const OVERLAY_POINT_RADIUS: f64 = 0.004;
+
+fn to_rgba(color: &Color) -> Rgba<u8> {
+    match color {
+        Color::Rgb(r, g, b) => Rgba([*r, *g, *b, u8::MAX]),
+        _ => Rgba([u8::MAX, u8::MAX, u8::MAX, u8::MAX]),
+    }
+}
+
+/// Collects the laser points, pointclouds and markers to project, in the static frame.
+fn overlay_points(listeners: &Listeners) -> Vec<(Point3<f64>, Rgba<u8>)> {
+    let mut points = Vec::new();
+    for laser in &listeners.lasers {
+        let c = &laser.config.color;
+        let color = Rgba([c.r, c.g, c.b, u8::MAX]);
+        points.extend(
+            laser
+                .points_3d
+                .read()
+                .unwrap()
+                .iter()
+                .map(|pt| (*pt, color)),
+        );
+    }
+    for pointcloud in &listeners.pointclouds {
+        points.extend(
+            pointcloud
+                .points
+                .read()
+                .unwrap()
+                .iter()
+                .map(|pt| (pt.point, to_rgba(&pt.color))),
+        );
+    }
+    for (pt, color) in listeners.markers.get_points() {
+        points.push((Point3::new(pt.0, pt.1, pt.2), to_rgba(&color)));
+    }
+    points
+}
+
+/// Projects a point in the camera frame to the pixel coordinates of the image, using the
+/// projection matrix of the camera, or its intrinsic matrix if the former is not set.
+fn project(info: &CameraInfo, pt: &Point3<f64>) -> Option<(f64, f64)> {
+    if pt.z <= 0.0 {
+        return None;
+    }
+    let (fx, fy, cx, cy, tx, ty) = if info.P[0] != 0.0 {
+        (
+            info.P[0], info.P[5], info.P[2], info.P[6], info.P[3], info.P[7],
+        )
+    } else if info.K[0] != 0.0 {
+        (info.K[0], info.K[4], info.K[2], info.K[5], 0.0, 0.0)
+    } else {
+        return None;
+    };
+    Some(((fx * pt.x + tx) / pt.z + cx, (fy * pt.y + ty) / pt.z + cy))
+}
+
+/// Draws the points, given in the camera frame, on the image rotated by `rotation` degrees.
+fn draw_projected_points(
+    img: &mut RgbaImage,
+    info: &CameraInfo,
+    rotation: i64,
+    points: &Vec<(Point3<f64>, Rgba<u8>)>,
+) {
+    let (width, height) = match rotation {
+        90 | 270 => (img.height() as f64, img.width() as f64),
+        _ => (img.width() as f64, img.height() as f64),
+    };
+    // The image may be scaled with respect to the calibration, e.g., with binning
+    let (scale_x, scale_y) = if info.width > 0 && info.height > 0 {
+        (width / info.width as f64, height / info.height as f64)
+    } else {
+        (1.0, 1.0)
+    };
+    let radius = (OVERLAY_POINT_RADIUS * width).ceil() as i64;
+    for (pt, color) in points {
+        let (u, v) = match project(info, pt) {
+            Some((u, v)) => (u * scale_x, v * scale_y),
+            None => continue,
+        };
+        if u < 0.0 || v < 0.0 || u >= width || v >= height {
+            continue;
+        }
+        let (u, v) = match rotation {
+            90 => (height - 1.0 - v, u),
+            180 => (width - 1.0 - u, height - 1.0 - v),
+            270 => (v, width - 1.0 - u),
+            _ => (u, v),
+        };
+        for dx in -radius..=radius {
+            for dy in -radius..=radius {
+                let (x, y) = (u as i64 + dx, v as i64 + dy);
+                if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+                    img.put_pixel(x as u32, y as u32, *color);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the number of columns and rows of the grid showing `n` images.
+fn grid_size(n: usize) -> (usize, usize) {
+    let cols = (n as f64).sqrt().ceil().max(1.0) as usize;
+    let rows = (n + cols - 1) / cols;
+    (cols, rows.max(1))
+}
+
+/// Splits `area` into the tiles of the grid, row by row.
+fn grid_tiles(area: Rect, n: usize) -> Vec<Rect> {
+    let (cols, rows) = grid_size(n);
+    let row_areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, rows as u32); rows])
+        .split(area);
+    let mut tiles = Vec::new();
+    for row_area in row_areas {
+        tiles.extend(
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, cols as u32); cols])
+                .split(row_area),
+        );
+    }
+    tiles.truncate(n);
+    tiles
+}
+
+/// Draws a crosshair centered on the given pixel, leaving the pixel itself visible.
+fn draw_cursor(img: &mut RgbaImage, cursor: (u32, u32)) {
+    let size = (img.width().max(img.height()) / 40).max(3) as i64;
+    let color = Rgba([255, 0, 255, u8::MAX]);
+    let (cx, cy) = (cursor.0 as i64, cursor.1 as i64);
+    for d in 2..=size {
+        for (x, y) in [(cx - d, cy), (cx + d, cy), (cx, cy - d), (cx, cy + d)].iter() {
+            if *x >= 0 && *y >= 0 && (*x as u32) < img.width() && (*y as u32) < img.height() {
+                img.put_pixel(*x as u32, *y as u32, color);
+            }
+        }
+    }
 }
 
 /// Represents the image view mode.
 impl ImageView {
-    pub fn new(image_topics: Vec<ImageListenerConfig>) -> ImageView {
+    pub fn new(
+        image_topics: Vec<ImageListenerConfig>,
+        viewport: Rc<RefCell<Viewport>>,
+    ) -> ImageView {
         let mut images: Vec<image::ImageListener> = Vec::new();
         for image_config in image_topics {
             images.push(image::ImageListener::new(image_config));
         }
         ImageView {
+            viewport: viewport,
             images: images,
             active_sub: 0,
+            maximized: false,
+            overlay: false,
+            paused: false,
+            cursor: (0, 0),
+            cursor_step: DEFAULT_CURSOR_STEP,
+            message: "".to_string(),
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        for sub in self.images.iter_mut() {
+            sub.set_paused(paused);
         }
+        self.center_cursor();
+    }
+
+    fn center_cursor(&mut self) {
+        let img = self.images[self.active_sub].img.read().unwrap();
+        self.cursor = (img.width() / 2, img.height() / 2);
+    }
+
+    fn move_cursor(&mut self, dx: i64, dy: i64) {
+        let img = self.images[self.active_sub].img.read().unwrap();
+        let step = self.cursor_step as i64;
+        let x = (self.cursor.0 as i64 + dx * step)
+            .min(img.width() as i64 - 1)
+            .max(0);
+        let y = (self.cursor.1 as i64 + dy * step)
+            .min(img.height() as i64 - 1)
+            .max(0);
+        self.cursor = (x as u32, y as u32);
+    }
+
+    /// Handles the keys used to inspect the paused image. Returns whether the key was used.
+    fn handle_cursor_input(&mut self, input: &String) -> bool {
+        match input.as_str() {
+            input::LEFT => self.move_cursor(-1, 0),
+            input::RIGHT => self.move_cursor(1, 0),
+            input::UP => self.move_cursor(0, -1),
+            input::DOWN => self.move_cursor(0, 1),
+            input::INCREMENT_STEP => self.cursor_step *= 2,
+            input::DECREMENT_STEP => self.cursor_step = (self.cursor_step / 2).max(1),
+            // The frozen images are not rotated
+            input::ROTATE_LEFT | input::ROTATE_RIGHT => (),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Saves the selected image at its original resolution, with the overlay if enabled.
+    fn save_snapshot(&mut self) {
+        let image_sub = &self.images[self.active_sub];
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = format!(
+            "{}_{}.png",
+            image_sub
+                .config
+                .topic
+                .trim_start_matches('/')
+                .replace('/', "_"),
+            stamp
+        );
+        if std::path::Path::new(&path).exists() {
+            self.message = format!("Not saved, {} already exists", path);
+            return;
+        }
+        self.message = match self.get_image(image_sub).save(&path) {
+            Ok(()) => format!("Saved {}", path),
+            Err(e) => format!("Could not save {}: {}", path, e),
+        };
+    }
+
+    /// Describes the pixel under the cursor, including its depth for depth images.
+    fn cursor_info(&self) -> String {
+        let image_sub = &self.images[self.active_sub];
+        let img = image_sub.img.read().unwrap();
+        let (x, y) = self.cursor;
+        if x >= img.width() || y >= img.height() {
+            return "No image".to_string();
+        }
+        let px = img.get_pixel(x, y);
+        let mut info = format!(
+            "Pixel ({}, {}): RGB ({}, {}, {})",
+            x, y, px[0], px[1], px[2]
+        );
+        if let Some(depth) = &*image_sub.depth.read().unwrap() {
+            info += &match depth.get_rotated(x, y, image_sub.rotation()) {
+                Some(d) if d.is_finite() && d > 0.0 => format!(", depth {:.3} m", d),
+                _ => ", depth invalid".to_string(),
+            };
+        }
+        info
+    }
+
+    /// Returns the last image received, with the overlay if enabled and available.
+    fn get_image(&self, image_sub: &ImageListener) -> RgbaImage {
+        let mut img = image_sub.img.read().unwrap().clone();
+        if !self.overlay {
+            return img;
+        }
+        let info = match &*image_sub.camera_info.read().unwrap() {
+            Some(info) => info.clone(),
+            None => return img,
+        };
+        let viewport = self.viewport.borrow();
+        let tf = match viewport.tf_listener.lookup_transform(
+            &info.header.frame_id,
+            &viewport.static_frame,
+            rosrust::Time::new(),
+        ) {
+            Ok(tf) => transformation::ros_transform_to_isometry(&tf.transform),
+            Err(_e) => return img,
+        };
+        let points = overlay_points(&viewport.listeners)
+            .into_iter()
+            .map(|(pt, color)| (tf * pt, color))
+            .collect();
+        draw_projected_points(&mut img, &info, image_sub.rotation(), &points);
+        img
+    }
+
+    /// Whether only the selected image is shown.
+    fn shows_single(&self) -> bool {
+        self.maximized || self.images.len() == 1
+    }
+
+    fn select(&mut self, offset: i64) {
+        let n = self.images.len() as i64;
+        self.active_sub = (self.active_sub as i64 + offset).rem_euclid(n) as usize;
     }
 }
 
 impl AppMode for ImageView {
     fn run(&mut self) {
-        if self.images.len() > 0 && !self.images[self.active_sub].is_active() {
-            self.images[self.active_sub].activate();
+        // In the grid all the images are received, otherwise only the selected one
+        let single = self.shows_single();
+        for (i, sub) in self.images.iter_mut().enumerate() {
+            let visible = !single || i == self.active_sub;
+            if visible && !sub.is_active() {
+                sub.activate();
+            } else if !visible && sub.is_active() {
+                sub.deactivate();
+            }
         }
     }
 
@@ -47,19 +353,19 @@ impl AppMode for ImageView {
 
     fn handle_input(&mut self, input: &String) {
         if self.images.len() > 0 {
+            let (cols, _) = grid_size(self.images.len());
+            if self.paused && self.handle_cursor_input(input) {
+                return;
+            }
             match input.as_str() {
-                input::LEFT | input::PREVIOUS => {
-                    self.images[self.active_sub].deactivate();
-                    self.active_sub = if self.active_sub > 0 {
-                        self.active_sub - 1
-                    } else {
-                        self.images.len() - 1
-                    };
-                }
-                input::RIGHT | input::NEXT => {
-                    self.images[self.active_sub].deactivate();
-                    self.active_sub = (self.active_sub + 1) % self.images.len();
-                }
+                input::LEFT | input::PREVIOUS => self.select(-1),
+                input::RIGHT | input::NEXT => self.select(1),
+                input::UP if !self.shows_single() => self.select(-(cols as i64)),
+                input::DOWN if !self.shows_single() => self.select(cols as i64),
+                input::CONFIRM => self.maximized = !self.maximized,
+                input::TOGGLE_OVERLAY => self.overlay = !self.overlay,
+                input::PAUSE => self.set_paused(!self.paused),
+                input::SAVE => self.save_snapshot(),
                 input::ROTATE_RIGHT => {
                     self.images[self.active_sub].rotate(90);
                 }
@@ -68,11 +374,23 @@ impl AppMode for ImageView {
                 }
                 _ => (),
             }
+            let changes_image = [input::NEXT, input::PREVIOUS, input::CONFIRM];
+            if self.paused && changes_image.contains(&input.as_str()) {
+                self.center_cursor();
+            }
         }
     }
 
     fn get_description(&self) -> Vec<String> {
-        vec!["This mode allows to visualize images received on the given topics.".to_string()]
+        vec![
+            "This mode allows to visualize images received on the given topics.".to_string(),
+            "The images are shown in a grid, in which the selected one can be maximized."
+                .to_string(),
+            "The laser scans, pointclouds and markers can be projected on the images using the camera info."
+                .to_string(),
+            "The images can be paused to inspect their pixels with a cursor, and saved as PNG."
+                .to_string(),
+        ]
     }
 
     fn get_keymap(&self) -> Vec<[String; 2]> {
@@ -85,6 +403,39 @@ impl AppMode for ImageView {
                 input::RIGHT.to_string(),
                 "Switches to the next image.".to_string(),
             ],
+            [
+                input::UP.to_string(),
+                "Selects the image above in the grid.".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Selects the image below in the grid.".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Maximizes the selected image or goes back to the grid.".to_string(),
+            ],
+            [
+                input::TOGGLE_OVERLAY.to_string(),
+                "Shows or hides the projected laser scans, pointclouds and markers.".to_string(),
+            ],
+            [
+                input::PAUSE.to_string(),
+                "Pauses or resumes the images. While paused, the movement keys move the cursor."
+                    .to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Increases the step of the cursor.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Decreases the step of the cursor.".to_string(),
+            ],
+            [
+                input::SAVE.to_string(),
+                "Saves the selected image as PNG in the current directory.".to_string(),
+            ],
             [
                 input::ROTATE_LEFT.to_string(),
                 "Rotates the image counter-clockwise.".to_string(),
@@ -116,24 +467,78 @@ impl<B: Backend> Drawable<B> for ImageView {
             .wrap(Wrap { trim: false });
             f.render_widget(header, chunks[0]);
         } else {
-            for image_sub in &self.images {
-                if image_sub.is_active() {
-                    let header = Paragraph::new(Spans::from(vec![
-                        Span::styled(
-                            self.get_name() + " view",
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                        ),
-                        Span::raw(" - Topic: /".to_string() + &image_sub.config.topic),
-                    ]))
-                    .block(Block::default().borders(Borders::NONE))
-                    .style(Style::default().fg(Color::White))
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: false });
-                    f.render_widget(header, chunks[0]);
-                    let image = image_sub.img.read().unwrap();
-                    let widget = Image::with_img(image.clone()).color_mode(ColorMode::Rgb);
-                    f.render_widget(widget, chunks[1]);
-                    break;
+            let selected = &self.images[self.active_sub];
+            let mut header_text = vec![
+                Span::styled(
+                    self.get_name() + " view",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" - Topic: /".to_string() + &selected.config.topic),
+            ];
+            if self.paused {
+                header_text.push(Span::styled(
+                    " - Paused - ".to_string() + &self.cursor_info(),
+                    Style::default().fg(Color::Magenta),
+                ));
+            }
+            if !self.message.is_empty() {
+                header_text.push(Span::raw(" - ".to_string() + &self.message));
+            }
+            if let Some(error) = &*selected.error.read().unwrap() {
+                header_text.push(Span::styled(
+                    " - ".to_string() + error,
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            if self.overlay {
+                header_text.push(Span::raw(
+                    if selected.camera_info.read().unwrap().is_some() {
+                        " - Overlay".to_string()
+                    } else {
+                        format!(" - Overlay: waiting for {}", selected.camera_info_topic())
+                    },
+                ));
+            }
+            if !self.shows_single() {
+                header_text.push(Span::raw(format!(
+                    " ({}/{})",
+                    self.active_sub + 1,
+                    self.images.len()
+                )));
+            }
+            let header = Paragraph::new(Spans::from(header_text))
+                .block(Block::default().borders(Borders::NONE))
+                .style(Style::default().fg(Color::White))
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: false });
+            f.render_widget(header, chunks[0]);
+            if self.shows_single() {
+                let mut image = self.get_image(selected);
+                if self.paused {
+                    draw_cursor(&mut image, self.cursor);
+                }
+                let widget = Image::with_img(image).color_mode(ColorMode::Rgb);
+                f.render_widget(widget, chunks[1]);
+            } else {
+                let tiles = grid_tiles(chunks[1], self.images.len());
+                for (i, (image_sub, tile)) in self.images.iter().zip(tiles).enumerate() {
+                    let border_style = if i == self.active_sub {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default().fg(Color::White)
+                    };
+                    let block = Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style)
+                        .title(Span::raw("/".to_string() + &image_sub.config.topic));
+                    let inner = block.inner(tile);
+                    f.render_widget(block, tile);
+                    let mut image = self.get_image(image_sub);
+                    if self.paused && i == self.active_sub {
+                        draw_cursor(&mut image, self.cursor);
+                    }
+                    let widget = Image::with_img(image).color_mode(ColorMode::Rgb);
+                    f.render_widget(widget, inner);
                 }
             }
         }
@@ -0,0 +1,318 @@
+use crate::app_modes::text_form::{FormEvent, TextForm};
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::rosout::{LogEntry, LogListener, Severity};
+use chrono::{Local, TimeZone};
+use crossterm::event::KeyCode;
+use regex::Regex;
+use std::sync::Arc;
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use tui::Frame;
+
+pub fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Debug => Style::default().fg(Color::DarkGray),
+        Severity::Info => Style::default().fg(Color::White),
+        Severity::Warn => Style::default().fg(Color::Yellow),
+        Severity::Error => Style::default().fg(Color::Red),
+        Severity::Fatal => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    }
+}
+
+fn format_stamp(stamp: &rosrust::Time) -> String {
+    format!(
+        "{}.{:03}",
+        Local
+            .timestamp(stamp.sec as i64, stamp.nsec)
+            .format("%H:%M:%S"),
+        stamp.nsec / 1_000_000
+    )
+}
+
+pub struct Logs {
+    listener: Arc<LogListener>,
+    min_severity: usize, // Index in Severity::ALL
+    node_filter: String,
+    regex: Option<Regex>,
+    frozen: Option<Vec<LogEntry>>,
+    scroll: usize, // Number of entries hidden below the view, 0 follows the new entries
+    form: Option<TextForm>,
+    message: String,
+}
+
+impl Logs {
+    pub fn new(listener: Arc<LogListener>) -> Logs {
+        Logs {
+            listener,
+            min_severity: 0,
+            node_filter: "".to_string(),
+            regex: None,
+            frozen: None,
+            scroll: 0,
+            form: None,
+            message: "".to_string(),
+        }
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        entry.severity >= Severity::ALL[self.min_severity]
+            && entry.node.contains(&self.node_filter)
+            && self
+                .regex
+                .as_ref()
+                .map_or(true, |regex| regex.is_match(&entry.msg))
+    }
+
+    fn filtered(&self) -> Vec<LogEntry> {
+        match &self.frozen {
+            Some(entries) => entries
+                .iter()
+                .filter(|entry| self.matches(entry))
+                .cloned()
+                .collect(),
+            None => self
+                .listener
+                .entries
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|entry| self.matches(entry))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.frozen = match self.frozen {
+            Some(_) => None,
+            None => Some(
+                self.listener
+                    .entries
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+        };
+    }
+
+    fn open_form(&mut self) {
+        let regex = self
+            .regex
+            .as_ref()
+            .map_or("".to_string(), |regex| regex.as_str().to_string());
+        self.form = Some(TextForm::new(
+            "Filters",
+            vec![
+                ("Node".to_string(), self.node_filter.clone()),
+                ("Regex".to_string(), regex),
+            ],
+        ));
+    }
+
+    fn apply_form(&mut self, form: &TextForm) {
+        self.node_filter = form.value(0).to_string();
+        self.regex = None;
+        self.message = "".to_string();
+        if !form.value(1).is_empty() {
+            match Regex::new(form.value(1)) {
+                Ok(regex) => self.regex = Some(regex),
+                Err(e) => self.message = format!("Error: invalid regex: {}", e),
+            }
+        }
+        self.scroll = 0;
+    }
+
+    fn clear_filters(&mut self) {
+        self.min_severity = 0;
+        self.node_filter = "".to_string();
+        self.regex = None;
+        self.scroll = 0;
+    }
+
+    fn header(&self) -> String {
+        let mut header = format!("Severity: {}+", Severity::ALL[self.min_severity].name());
+        if !self.node_filter.is_empty() {
+            header += &format!(", Node: {}", self.node_filter);
+        }
+        if let Some(regex) = &self.regex {
+            header += &format!(", Regex: {}", regex.as_str());
+        }
+        if self.frozen.is_some() {
+            header += ", Paused";
+        }
+        if !self.message.is_empty() {
+            header += &format!(", {}", self.message);
+        }
+        header
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Logs {}
+
+impl AppMode for Logs {
+    fn run(&mut self) {
+        // The messages shown here are considered read
+        self.listener.reset_counts();
+    }
+
+    fn reset(&mut self) {
+        self.form = None;
+        self.listener.reset_counts();
+    }
+
+    fn is_editing(&self) -> bool {
+        self.form.is_some()
+    }
+
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        let event = match &mut self.form {
+            Some(form) => form.handle_key(key),
+            None => return,
+        };
+        match event {
+            FormEvent::Confirm => {
+                let form = self.form.take().unwrap();
+                self.apply_form(&form);
+            }
+            FormEvent::Cancel => self.form = None,
+            FormEvent::None => (),
+        }
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Logs shows the log messages of the nodes, received from rosout.".to_string(),
+            "The messages can be filtered by severity, node name and regular expression."
+                .to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::UP => {
+                // Stops at the oldest entry, so that scrolling back moves right away
+                self.scroll = (self.scroll + 1).min(self.filtered().len().saturating_sub(1))
+            }
+            input::DOWN => self.scroll = self.scroll.saturating_sub(1),
+            input::NEXT => {
+                self.min_severity = (self.min_severity + 1).min(Severity::ALL.len() - 1);
+                self.scroll = 0;
+            }
+            input::PREVIOUS => {
+                self.min_severity = self.min_severity.saturating_sub(1);
+                self.scroll = 0;
+            }
+            input::PAUSE => self.toggle_pause(),
+            input::EDIT => self.open_form(),
+            input::CANCEL => self.clear_filters(),
+            _ => (),
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Scrolls to older messages".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Scrolls to newer messages".to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Raises the minimum severity".to_string(),
+            ],
+            [
+                input::PREVIOUS.to_string(),
+                "Lowers the minimum severity".to_string(),
+            ],
+            [
+                input::EDIT.to_string(),
+                "Sets the node and regex filters".to_string(),
+            ],
+            [input::CANCEL.to_string(), "Clears the filters".to_string()],
+            [
+                input::PAUSE.to_string(),
+                "Pauses/resumes the update of the messages".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Logs".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for Logs {
+    fn draw(&self, f: &mut Frame<B>) {
+        let title_text = vec![Spans::from(Span::styled(
+            "Logs",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(1),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = Paragraph::new(Spans::from(Span::raw(self.header())))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center);
+
+        let entries = self.filtered();
+        let visible = areas[2].height.saturating_sub(3) as usize; // Borders + table header
+        let scroll = self.scroll.min(entries.len().saturating_sub(visible));
+        let end = entries.len() - scroll;
+        let start = end.saturating_sub(visible);
+        let rows = entries[start..end].iter().map(|entry| {
+            Row::new(vec![
+                Cell::from(format_stamp(&entry.stamp)),
+                Cell::from(entry.severity.name()),
+                Cell::from(entry.node.clone()),
+                Cell::from(entry.msg.clone()),
+            ])
+            .style(severity_style(entry.severity))
+        });
+        let table = Table::new(rows)
+            .block(
+                Block::default()
+                    .title(format!(" {} messages ", entries.len()))
+                    .borders(Borders::ALL),
+            )
+            .header(
+                Row::new(vec!["Time", "Severity", "Node", "Message"])
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .widths(&[
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Percentage(20),
+                Constraint::Percentage(80),
+            ])
+            .column_spacing(1);
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+        f.render_widget(table, areas[2]);
+
+        if let Some(form) = &self.form {
+            let area = f.size();
+            form.draw(f, area);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Map view mode allows to move freely around the map, e.g., to inspect a map loaded from file.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::widgets::canvas::Context;
+use tui::Frame;
+
+/// Distance by which the view is moved, relative to the visible area.
+const PAN_STEP: f64 = 0.1;
+/// Angle by which the view is rotated, in radians.
+const ROTATION_STEP: f64 = std::f64::consts::PI / 12.0;
+
+pub struct MapView {
+    viewport: Rc<RefCell<Viewport>>,
+}
+
+impl MapView {
+    pub fn new(viewport: Rc<RefCell<Viewport>>) -> MapView {
+        MapView { viewport }
+    }
+
+    /// Moves the view center by a fraction of the visible area, along the axes of the view.
+    fn pan(&mut self, x: f64, y: f64) {
+        let mut viewport = self.viewport.borrow_mut();
+        let center = viewport.view_center();
+        let (x_bounds, y_bounds) = viewport.bounds_around(center);
+        let dx = x * PAN_STEP * (x_bounds[1] - x_bounds[0]);
+        let dy = y * PAN_STEP * (y_bounds[1] - y_bounds[0]);
+        let (sin, cos) = viewport.rotation.sin_cos();
+        viewport.center = (
+            center.0 + dx * cos - dy * sin,
+            center.1 + dx * sin + dy * cos,
+        );
+        viewport.follow_robot = false;
+    }
+}
+
+impl<B: Backend> BaseMode<B> for MapView {}
+
+impl AppMode for MapView {
+    fn run(&mut self) {}
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        match input.as_str() {
+            input::UP => self.pan(0.0, 1.0),
+            input::DOWN => self.pan(0.0, -1.0),
+            input::LEFT => self.pan(-1.0, 0.0),
+            input::RIGHT => self.pan(1.0, 0.0),
+            input::ROTATE_LEFT => self.viewport.borrow_mut().rotation += ROTATION_STEP,
+            input::ROTATE_RIGHT => self.viewport.borrow_mut().rotation -= ROTATION_STEP,
+            input::CANCEL => {
+                let mut viewport = self.viewport.borrow_mut();
+                viewport.follow_robot = true;
+                viewport.rotation = 0.0;
+            }
+            _ => (),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "Map".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode allows to move and rotate the view freely.".to_string(),
+            "The view stops following the robot until it is reset.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [input::UP.to_string(), "Moves the view up.".to_string()],
+            [input::DOWN.to_string(), "Moves the view down.".to_string()],
+            [input::LEFT.to_string(), "Moves the view left.".to_string()],
+            [
+                input::RIGHT.to_string(),
+                "Moves the view right.".to_string(),
+            ],
+            [
+                input::ROTATE_LEFT.to_string(),
+                "Rotates the view counter-clockwise.".to_string(),
+            ],
+            [
+                input::ROTATE_RIGHT.to_string(),
+                "Rotates the view clockwise.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Centers the view on the robot again.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for MapView {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn info(&self) -> String {
+        let viewport = self.viewport.borrow();
+        let center = viewport.view_center();
+        let mut info = format!("Center: ({:.2}, {:.2})", center.0, center.1);
+        let viewport_info = viewport.info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+    }
+}
@@ -1,11 +1,24 @@
 //! A module that contains all the builing blocks to create app modes, as well as the app modes themselves.
 
+pub mod diagnostics;
+pub mod ghost_pose;
 pub mod image_view;
+pub mod logs;
+pub mod map_view;
+pub mod parameters;
+pub mod playback;
+pub mod plot;
+pub mod send_goal;
 pub mod send_pose;
+pub mod service_caller;
 pub mod teleoperate;
+pub mod text_form;
+pub mod topic_echo;
 pub mod topic_managment;
 pub mod viewport;
+pub mod waypoints;
 
+use crossterm::event::KeyCode;
 use tui::backend::Backend;
 use tui::Frame;
 
@@ -21,6 +34,8 @@ pub mod input {
     pub const MODE_7: &str = "Switch to mode 7";
     pub const MODE_8: &str = "Switch to mode 8";
     pub const MODE_9: &str = "Switch to mode 9";
+    pub const NEXT_MODE: &str = "Next mode";
+    pub const PREVIOUS_MODE: &str = "Previous mode";
     pub const LEFT: &str = "Left";
     pub const RIGHT: &str = "Right";
     pub const UP: &str = "Up";
@@ -29,12 +44,33 @@ pub mod input {
     pub const ROTATE_RIGHT: &str = "Clockwise rotation";
     pub const CONFIRM: &str = "Confirm";
     pub const CANCEL: &str = "Cancel";
+    pub const CANCEL_GOAL: &str = "Cancel goal";
     pub const ZOOM_IN: &str = "Zoom in";
     pub const ZOOM_OUT: &str = "Zoom out";
     pub const INCREMENT_STEP: &str = "Increment step";
     pub const DECREMENT_STEP: &str = "Decrement step";
     pub const NEXT: &str = "Next";
     pub const PREVIOUS: &str = "Previous";
+    pub const DELETE: &str = "Delete";
+    pub const MOVE_FORWARD: &str = "Move forward";
+    pub const MOVE_BACKWARD: &str = "Move backward";
+    pub const EXECUTE: &str = "Execute";
+    pub const PUBLISH: &str = "Publish";
+    pub const SAVE: &str = "Save";
+    pub const LOAD: &str = "Load";
+    pub const EDIT: &str = "Edit";
+    pub const NEXT_VIEW: &str = "Next view";
+    pub const SAVE_VIEW: &str = "Save view";
+    pub const PICK_VIEW: &str = "Pick view";
+    pub const TOGGLE_OVERLAY: &str = "Toggle overlay";
+    pub const PAUSE: &str = "Pause";
+    pub const TOGGLE_INSET: &str = "Toggle inset";
+    pub const GROW_INSET: &str = "Grow inset";
+    pub const SHRINK_INSET: &str = "Shrink inset";
+    pub const TOGGLE_AUTOSCALE: &str = "Toggle autoscale";
+    pub const RECORD: &str = "Record";
+    pub const NEXT_MAP: &str = "Next map";
+    pub const SAVE_MAP: &str = "Save map";
     pub const SHOW_HELP: &str = "Show help";
     pub const UNMAPPED: &str = "Any other";
 }
@@ -62,6 +98,18 @@ pub trait AppMode {
 
     /// Returns the name of the mode.
     fn get_name(&self) -> String;
+
+    /// Returns true if the mode is capturing raw key presses, e.g., to type in a text field.
+    /// While editing, the keys are passed to `handle_text_input` instead of being mapped.
+    fn is_editing(&self) -> bool {
+        false
+    }
+
+    /// Handles a raw key press while the mode is editing.
+    ///
+    /// # Arguments
+    /// - `_key` : the key that was pressed
+    fn handle_text_input(&mut self, _key: &KeyCode) {}
 }
 
 /// Represents something that can be drawn on the screen
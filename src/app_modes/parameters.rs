@@ -0,0 +1,490 @@
+use crate::app_modes::text_form::{FormEvent, TextForm};
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crossterm::event::KeyCode;
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fmt;
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use tui::Frame;
+
+/// A line of the parameter tree.
+struct TreeLine {
+    path: String,
+    depth: usize,
+    text: String,
+    expandable: bool,
+    expanded: bool,
+    editable: bool, // Scalars that are parameters on their own, i.e., not in a list
+}
+
+/// A new value for a scalar parameter, with the type of the current value.
+enum ParamValue {
+    Bool(bool),
+    Int(i32),
+    Double(f64),
+    String(String),
+}
+
+impl ParamValue {
+    /// Parses the typed text with the type of the current value of the parameter.
+    fn parse(current: &Value, text: &str) -> Result<ParamValue, String> {
+        let invalid = |kind: &str| format!("'{}' is not a valid {}", text, kind);
+        match current {
+            Value::Bool(_) => match text.to_lowercase().as_str() {
+                "true" => Ok(ParamValue::Bool(true)),
+                "false" => Ok(ParamValue::Bool(false)),
+                _ => Err(invalid("boolean")),
+            },
+            Value::Number(n) if n.is_f64() => text
+                .parse()
+                .map(ParamValue::Double)
+                .map_err(|_| invalid("number")),
+            Value::Number(_) => text
+                .parse()
+                .map(ParamValue::Int)
+                .map_err(|_| invalid("integer")),
+            _ => Ok(ParamValue::String(text.to_string())),
+        }
+    }
+
+    fn set(&self, name: &str) -> Result<(), String> {
+        let param =
+            rosrust::param(name).ok_or_else(|| format!("invalid parameter name {}", name))?;
+        match self {
+            ParamValue::Bool(v) => param.set(v),
+            ParamValue::Int(v) => param.set(v),
+            ParamValue::Double(v) => param.set(v),
+            ParamValue::String(v) => param.set(v),
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamValue::Bool(v) => write!(f, "{}", v),
+            ParamValue::Int(v) => write!(f, "{}", v),
+            ParamValue::Double(v) => write!(f, "{}", v),
+            ParamValue::String(v) => write!(f, "\"{}\"", v),
+        }
+    }
+}
+
+fn format_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::Number(v) => v.to_string(),
+        Value::String(v) => format!("\"{}\"", v),
+        _ => "".to_string(),
+    }
+}
+
+fn key_to_string(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => format_scalar(other),
+    }
+}
+
+/// Flattens the parameter tree into the lines to display, skipping the children of collapsed
+/// nodes. Namespaces and lists are collapsed unless expanded by the user.
+fn flatten(
+    value: &Value,
+    name: &str,
+    path: &str,
+    depth: usize,
+    in_list: bool,
+    expanded: &HashSet<String>,
+    lines: &mut Vec<TreeLine>,
+) {
+    let (children, is_list): (Vec<(String, &Value)>, bool) = match value {
+        Value::Mapping(mapping) => {
+            let mut children: Vec<(String, &Value)> =
+                mapping.iter().map(|(k, v)| (key_to_string(k), v)).collect();
+            children.sort_by(|a, b| a.0.cmp(&b.0));
+            (children, false)
+        }
+        Value::Sequence(items) => (
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            true,
+        ),
+        _ => {
+            lines.push(TreeLine {
+                path: path.to_string(),
+                depth,
+                text: format!("{}: {}", name, format_scalar(value)),
+                expandable: false,
+                expanded: false,
+                editable: !in_list,
+            });
+            return;
+        }
+    };
+    let is_expanded = expanded.contains(path);
+    let text = match value {
+        Value::Sequence(items) => format!("{} [{}]", name, items.len()),
+        _ => format!("{}/", name),
+    };
+    lines.push(TreeLine {
+        path: path.to_string(),
+        depth,
+        text,
+        expandable: true,
+        expanded: is_expanded,
+        editable: false,
+    });
+    if is_expanded {
+        for (child_name, child) in children {
+            let child_path = format!("{}/{}", path, child_name);
+            flatten(
+                child,
+                &child_name,
+                &child_path,
+                depth + 1,
+                in_list || is_list,
+                expanded,
+                lines,
+            );
+        }
+    }
+}
+
+/// Returns the value at the given path of the parameter tree.
+fn get_value<'a>(tree: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .try_fold(tree, |value, name| match value {
+            Value::Mapping(mapping) => mapping.get(&Value::String(name.to_string())),
+            _ => None,
+        })
+}
+
+pub struct Parameters {
+    tree: Option<Value>,
+    expanded: HashSet<String>,
+    lines: Vec<TreeLine>,
+    lines_state: ListState,
+    form: Option<(String, TextForm)>,
+    pending: Option<(String, ParamValue)>,
+    message: String,
+}
+
+impl Parameters {
+    pub fn new() -> Parameters {
+        Parameters {
+            tree: None,
+            expanded: HashSet::new(),
+            lines: Vec::new(),
+            lines_state: ListState::default(),
+            form: None,
+            pending: None,
+            message: "".to_string(),
+        }
+    }
+
+    fn update_tree(&mut self) {
+        self.tree = match rosrust::param("/").map(|param| param.get::<Value>()) {
+            Some(Ok(tree)) => Some(tree),
+            Some(Err(e)) => {
+                self.message = format!("Error: cannot read the parameters: {}", e);
+                None
+            }
+            None => None,
+        };
+        self.update_lines();
+    }
+
+    fn update_lines(&mut self) {
+        self.lines.clear();
+        if let Some(Value::Mapping(mapping)) = &self.tree {
+            let mut names: Vec<(String, &Value)> =
+                mapping.iter().map(|(k, v)| (key_to_string(k), v)).collect();
+            names.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, value) in names {
+                let path = format!("/{}", name);
+                flatten(
+                    value,
+                    &name,
+                    &path,
+                    0,
+                    false,
+                    &self.expanded,
+                    &mut self.lines,
+                );
+            }
+        }
+        let selected = match self.lines_state.selected() {
+            _ if self.lines.is_empty() => None,
+            Some(i) => Some(i.min(self.lines.len() - 1)),
+            None => Some(0),
+        };
+        self.lines_state.select(selected);
+    }
+
+    fn selected_line(&self) -> Option<&TreeLine> {
+        self.lines_state.selected().and_then(|i| self.lines.get(i))
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        let path = match self.selected_line() {
+            Some(line) if line.expandable && line.expanded != expanded => line.path.clone(),
+            _ => return,
+        };
+        if expanded {
+            self.expanded.insert(path);
+        } else {
+            self.expanded.remove(&path);
+        }
+        self.update_lines();
+    }
+
+    fn select(&mut self, offset: i64) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let i = match self.lines_state.selected() {
+            Some(i) => (i as i64 + offset).rem_euclid(self.lines.len() as i64) as usize,
+            None => 0,
+        };
+        self.lines_state.select(Some(i));
+    }
+
+    fn confirm(&mut self) {
+        let line = match self.selected_line() {
+            Some(line) => line,
+            None => return,
+        };
+        if line.expandable {
+            let expanded = !line.expanded;
+            self.set_expanded(expanded);
+        } else if line.editable {
+            let path = line.path.clone();
+            let current = match self.tree.as_ref().and_then(|tree| get_value(tree, &path)) {
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => format_scalar(value),
+                None => return,
+            };
+            let form = TextForm::new(
+                &format!("Edit {}", path),
+                vec![("Value".to_string(), current)],
+            );
+            self.form = Some((path, form));
+        } else {
+            self.message = "Only scalar parameters can be edited".to_string();
+        }
+    }
+
+    /// Checks the value typed in the form and asks for confirmation.
+    fn apply_form(&mut self, path: String, form: &TextForm) {
+        let current = match self.tree.as_ref().and_then(|tree| get_value(tree, &path)) {
+            Some(value) => value,
+            None => return,
+        };
+        match ParamValue::parse(current, form.value(0)) {
+            Ok(value) => self.pending = Some((path, value)),
+            Err(e) => self.message = format!("Error: {}", e),
+        }
+    }
+}
+
+/// Draws a popup asking to confirm an action.
+fn draw_prompt<B: Backend>(f: &mut Frame<B>, area: Rect, text: String) {
+    let width = area.width * 3 / 5;
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height.saturating_sub(4) / 2,
+        width,
+        4.min(area.height),
+    );
+    let prompt = Paragraph::new(vec![
+        Spans::from(Span::raw(text)),
+        Spans::from(Span::styled(
+            "Enter: confirm, Esc: cancel",
+            Style::default().fg(Color::Yellow),
+        )),
+    ])
+    .block(Block::default().title(" Confirm ").borders(Borders::ALL))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: false });
+    f.render_widget(Clear, popup);
+    f.render_widget(prompt, popup);
+}
+
+impl<B: Backend> BaseMode<B> for Parameters {}
+
+impl AppMode for Parameters {
+    fn run(&mut self) {}
+
+    fn reset(&mut self) {
+        self.form = None;
+        self.pending = None;
+        self.message = "".to_string();
+        self.update_tree();
+    }
+
+    fn is_editing(&self) -> bool {
+        self.form.is_some() || self.pending.is_some()
+    }
+
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        if let Some((path, value)) = self.pending.take() {
+            match key {
+                KeyCode::Enter => {
+                    self.message = match value.set(&path) {
+                        Ok(_) => format!("Set {} to {}", path, value),
+                        Err(e) => format!("Error: cannot set {}: {}", path, e),
+                    };
+                    self.update_tree();
+                }
+                KeyCode::Esc => (),
+                _ => self.pending = Some((path, value)),
+            }
+            return;
+        }
+        let event = match &mut self.form {
+            Some((_, form)) => form.handle_key(key),
+            None => return,
+        };
+        match event {
+            FormEvent::Confirm => {
+                let (path, form) = self.form.take().unwrap();
+                self.apply_form(path, &form);
+            }
+            FormEvent::Cancel => self.form = None,
+            FormEvent::None => (),
+        }
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Parameters allows to browse the parameter server and to edit scalar parameters."
+                .to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::UP => self.select(-1),
+            input::DOWN => self.select(1),
+            input::RIGHT => self.set_expanded(true),
+            input::LEFT => self.set_expanded(false),
+            input::CONFIRM => self.confirm(),
+            input::NEXT => {
+                self.message = "".to_string();
+                self.update_tree();
+            }
+            _ => (),
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Selects the previous parameter".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Selects the next parameter".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Expands the selected namespace or list".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Collapses the selected namespace or list".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Expands/collapses the selected namespace, or edits the selected parameter"
+                    .to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Reloads the parameters".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Parameters".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for Parameters {
+    fn draw(&self, f: &mut Frame<B>) {
+        let title_text = vec![Spans::from(Span::styled(
+            "Parameters",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .horizontal_margin(20)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(2),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = match self.selected_line() {
+            _ if !self.message.is_empty() => self.message.clone(),
+            Some(line) => line.path.clone(),
+            None => "No parameters".to_string(),
+        };
+        let header = Paragraph::new(Spans::from(Span::raw(header)))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let items: Vec<ListItem> = self
+            .lines
+            .iter()
+            .map(|line| {
+                let marker = match (line.expandable, line.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    _ => "  ",
+                };
+                ListItem::new(format!(
+                    "{}{}{}",
+                    "  ".repeat(line.depth),
+                    marker,
+                    line.text
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().title("Parameters").borders(Borders::ALL))
+            .highlight_symbol(">> ");
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+        f.render_stateful_widget(list, areas[2], &mut self.lines_state.clone());
+
+        let area = f.size();
+        if let Some((_, form)) = &self.form {
+            form.draw(f, area);
+        }
+        if let Some((path, value)) = &self.pending {
+            draw_prompt(f, area, format!("Set {} to {}?", path, value));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Playback mode controls the playback of a bag, shown in the viewport.
+
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::player::Player;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::widgets::canvas::Context;
+use tui::Frame;
+
+/// Initial seek step, in seconds.
+const DEFAULT_SEEK_STEP: f64 = 5.0;
+
+pub struct Playback {
+    viewport: Rc<RefCell<Viewport>>,
+    player: Rc<RefCell<Player>>,
+    seek_step: f64,
+}
+
+impl Playback {
+    pub fn new(viewport: Rc<RefCell<Viewport>>, player: Rc<RefCell<Player>>) -> Playback {
+        Playback {
+            viewport,
+            player,
+            seek_step: DEFAULT_SEEK_STEP,
+        }
+    }
+
+    fn seek_by(&mut self, seconds: f64) {
+        let mut player = self.player.borrow_mut();
+        let time = player.elapsed() + seconds;
+        player.seek(time);
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Playback {}
+
+impl AppMode for Playback {
+    fn run(&mut self) {}
+
+    fn reset(&mut self) {}
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        match input.as_str() {
+            input::PAUSE => self.player.borrow_mut().toggle_pause(),
+            input::RIGHT => self.seek_by(self.seek_step),
+            input::LEFT => self.seek_by(-self.seek_step),
+            input::INCREMENT_STEP => self.seek_step *= 2.0,
+            input::DECREMENT_STEP => self.seek_step = (self.seek_step / 2.0).max(1.0),
+            _ => (),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "Playback".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode plays back a bag given with --bag, without a ROS connection.".to_string(),
+            "The messages are passed to the listeners as if they were received from ROS."
+                .to_string(),
+            "The playback continues in the other modes.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = vec![
+            [
+                input::PAUSE.to_string(),
+                "Pauses or resumes the playback.".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Jumps forward by the seek step.".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Jumps backward by the seek step.".to_string(),
+            ],
+            [
+                input::INCREMENT_STEP.to_string(),
+                "Doubles the seek step.".to_string(),
+            ],
+            [
+                input::DECREMENT_STEP.to_string(),
+                "Halves the seek step.".to_string(),
+            ],
+        ];
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for Playback {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().x_bounds()
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.viewport.borrow().y_bounds()
+    }
+
+    fn info(&self) -> String {
+        let player = self.player.borrow();
+        let mut info = format!(
+            "{}: {:.1}/{:.1}s, {}, Seek step: {}s",
+            player.name,
+            player.elapsed(),
+            player.duration(),
+            if player.playing { "Playing" } else { "Paused" },
+            self.seek_step
+        );
+        if let Some(error) = &player.error {
+            info += &format!(", Error: {}", error);
+        }
+        let viewport_info = self.viewport.borrow().info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+    }
+}
@@ -0,0 +1,531 @@
+use crate::app_modes::text_form::{FormEvent, TextForm};
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::config::{self, PlotConfig};
+use crate::dynamic::{get_field, to_f64, DynamicListener, MessageCallback};
+use crossterm::event::KeyCode;
+use rosrust::MsgValue;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::symbols;
+use tui::text::{Span, Spans};
+use tui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Wrap};
+use tui::Frame;
+
+/// Data older than this is dropped, it is also the largest time window.
+const MAX_TIME_WINDOW: f64 = 600.;
+const MIN_TIME_WINDOW: f64 = 1.;
+/// Period at which the topics of the fields not published yet are looked up.
+const RESOLUTION_PERIOD: Duration = Duration::from_secs(1);
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Red,
+    Color::Blue,
+];
+
+type Points = Arc<Mutex<VecDeque<(f64, f64)>>>;
+
+/// Splits a field into its topic and the path of the field in the messages, using the longest
+/// matching topic.
+fn split_field(field: &str, topics: &[String]) -> Option<(String, String)> {
+    let field = format!("/{}", field.trim_start_matches('/'));
+    topics
+        .iter()
+        .filter(|topic| field.starts_with(&format!("{}/", topic)))
+        .max_by_key(|topic| topic.len())
+        .map(|topic| (topic.clone(), field[topic.len() + 1..].to_string()))
+}
+
+/// Returns the range covered by the values, with a margin.
+fn autoscale(data: &[Vec<(f64, f64)>]) -> [f64; 2] {
+    let mut bounds = [f64::INFINITY, f64::NEG_INFINITY];
+    for (_, value) in data.iter().flatten() {
+        bounds[0] = bounds[0].min(*value);
+        bounds[1] = bounds[1].max(*value);
+    }
+    if bounds[0] > bounds[1] {
+        return [-1., 1.];
+    }
+    let margin = match (bounds[1] - bounds[0]) * 0.05 {
+        m if m > 0. => m,
+        _ => 1.,
+    };
+    [bounds[0] - margin, bounds[1] + margin]
+}
+
+struct Series {
+    field: String,
+    topic: Option<String>,
+    path: String,
+    points: Points,
+}
+
+pub struct Plot {
+    plots: Vec<PlotConfig>,
+    current: usize,
+    loaded: bool,
+    series: Vec<Series>,
+    listeners: Vec<DynamicListener>,
+    start: Instant,
+    last_resolution: Option<Instant>,
+    time_window: f64,
+    autoscale: bool,
+    y_bounds: [f64; 2],
+    frozen: Option<Vec<Vec<(f64, f64)>>>,
+    form: Option<TextForm>,
+    message: String,
+}
+
+impl Plot {
+    pub fn new(plots: Vec<PlotConfig>) -> Plot {
+        Plot {
+            plots,
+            current: 0,
+            loaded: false,
+            series: Vec::new(),
+            listeners: Vec::new(),
+            start: Instant::now(),
+            last_resolution: None,
+            time_window: 10.,
+            autoscale: true,
+            y_bounds: [-1., 1.],
+            frozen: None,
+            form: None,
+            message: "".to_string(),
+        }
+    }
+
+    fn load_plot(&mut self) {
+        self.listeners.clear();
+        self.series.clear();
+        self.last_resolution = None;
+        self.frozen = None;
+        self.loaded = true;
+        let plot = match self.plots.get(self.current) {
+            Some(plot) => plot,
+            None => return,
+        };
+        self.time_window = plot.time_window.max(MIN_TIME_WINDOW).min(MAX_TIME_WINDOW);
+        match plot.y_range {
+            Some((min, max)) => {
+                self.autoscale = false;
+                self.y_bounds = [min, max];
+            }
+            None => self.autoscale = true,
+        }
+        self.series = plot
+            .fields
+            .iter()
+            .map(|field| Series {
+                field: field.clone(),
+                topic: None,
+                path: "".to_string(),
+                points: Arc::new(Mutex::new(VecDeque::new())),
+            })
+            .collect();
+    }
+
+    /// Looks up the topics of the fields that are not subscribed yet.
+    fn resolve_topics(&mut self) {
+        if self.series.iter().all(|series| series.topic.is_some()) {
+            return;
+        }
+        if let Some(last) = self.last_resolution {
+            if last.elapsed() < RESOLUTION_PERIOD {
+                return;
+            }
+        }
+        self.last_resolution = Some(Instant::now());
+        let topics: Vec<String> = match rosrust::topics() {
+            Ok(topics) => topics.into_iter().map(|topic| topic.name).collect(),
+            Err(_) => return,
+        };
+        let mut resolved = false;
+        for series in self.series.iter_mut().filter(|s| s.topic.is_none()) {
+            if let Some((topic, path)) = split_field(&series.field, &topics) {
+                series.topic = Some(topic);
+                series.path = path;
+                resolved = true;
+            }
+        }
+        if resolved {
+            self.subscribe();
+        }
+    }
+
+    /// Subscribes to the topics of the fields, one subscriber per topic.
+    fn subscribe(&mut self) {
+        let mut topics: Vec<String> = self
+            .series
+            .iter()
+            .filter_map(|series| series.topic.clone())
+            .collect();
+        topics.sort();
+        topics.dedup();
+        self.listeners.clear();
+        for topic in topics {
+            let targets: Vec<(String, Points)> = self
+                .series
+                .iter()
+                .filter(|series| series.topic.as_ref() == Some(&topic))
+                .map(|series| (series.path.clone(), series.points.clone()))
+                .collect();
+            let start = self.start;
+            let callback: MessageCallback = Box::new(move |msg: &MsgValue| {
+                let now = start.elapsed().as_secs_f64();
+                for (path, points) in targets.iter() {
+                    if let Some(value) = get_field(msg, path).and_then(to_f64) {
+                        let mut points = points.lock().unwrap();
+                        points.push_back((now, value));
+                        while let Some((stamp, _)) = points.front() {
+                            if now - stamp > MAX_TIME_WINDOW {
+                                points.pop_front();
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+            match DynamicListener::new(&topic, Some(callback)) {
+                Ok(listener) => self.listeners.push(listener),
+                Err(e) => self.message = format!("Error: cannot subscribe to {}: {}", topic, e),
+            }
+        }
+    }
+
+    /// Returns the points of each field, with their time relative to the current time.
+    fn data(&self) -> Vec<Vec<(f64, f64)>> {
+        if let Some(frozen) = &self.frozen {
+            return frozen.clone();
+        }
+        let now = self.start.elapsed().as_secs_f64();
+        self.series
+            .iter()
+            .map(|series| {
+                series
+                    .points
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(stamp, value)| (stamp - now, *value))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the points within the time window.
+    fn visible_data(&self) -> Vec<Vec<(f64, f64)>> {
+        self.data()
+            .into_iter()
+            .map(|points| {
+                points
+                    .into_iter()
+                    .filter(|(t, _)| *t >= -self.time_window)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn toggle_pause(&mut self) {
+        self.frozen = match self.frozen {
+            Some(_) => None,
+            None => Some(self.data()),
+        };
+    }
+
+    fn toggle_autoscale(&mut self) {
+        if self.autoscale {
+            // Keep the current bounds
+            self.y_bounds = autoscale(&self.visible_data());
+        }
+        self.autoscale = !self.autoscale;
+    }
+
+    fn switch_plot(&mut self, offset: i64) {
+        if self.plots.is_empty() {
+            return;
+        }
+        self.current = (self.current as i64 + offset).rem_euclid(self.plots.len() as i64) as usize;
+        self.load_plot();
+    }
+
+    fn add_field(&mut self, field: &str) {
+        if field.is_empty() {
+            return;
+        }
+        if self.plots.is_empty() {
+            self.plots.push(PlotConfig {
+                name: "Plot 1".to_string(),
+                fields: Vec::new(),
+                time_window: self.time_window,
+                y_range: None,
+            });
+            self.current = 0;
+        }
+        self.plots[self.current].fields.push(field.to_string());
+        self.load_plot();
+    }
+
+    fn remove_field(&mut self) {
+        if let Some(plot) = self.plots.get_mut(self.current) {
+            plot.fields.pop();
+            self.load_plot();
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(plot) = self.plots.get_mut(self.current) {
+            plot.time_window = self.time_window;
+            plot.y_range = if self.autoscale {
+                None
+            } else {
+                Some((self.y_bounds[0], self.y_bounds[1]))
+            };
+        }
+        let plots = self.plots.clone();
+        self.message = match config::update_user_config(|cfg| cfg.plots = plots) {
+            Ok(_) => "Plots saved to the configuration".to_string(),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    fn header(&self) -> Vec<Spans> {
+        let mut state = format!("Time window: {} s", self.time_window);
+        if self.autoscale {
+            state += " | Autoscale";
+        }
+        if self.frozen.is_some() {
+            state += " | Paused";
+        }
+        let mut spans = vec![Spans::from(Span::raw(state))];
+        let waiting: Vec<&str> = self
+            .series
+            .iter()
+            .filter(|series| series.topic.is_none())
+            .map(|series| series.field.as_str())
+            .collect();
+        if !waiting.is_empty() {
+            spans.push(Spans::from(Span::styled(
+                format!("Waiting for the topics of: {}", waiting.join(", ")),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        if let Some(e) = self
+            .listeners
+            .iter()
+            .find_map(|listener| listener.error.read().unwrap().clone())
+        {
+            spans.push(Spans::from(Span::styled(
+                e,
+                Style::default().fg(Color::Red),
+            )));
+        }
+        if !self.message.is_empty() {
+            spans.push(Spans::from(Span::raw(self.message.clone())));
+        }
+        spans
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Plot {}
+
+impl AppMode for Plot {
+    fn run(&mut self) {
+        if !self.loaded {
+            self.load_plot();
+        }
+        self.resolve_topics();
+    }
+
+    fn reset(&mut self) {
+        self.listeners.clear();
+        self.series.clear();
+        self.loaded = false;
+        self.form = None;
+        self.message = "".to_string();
+    }
+
+    fn is_editing(&self) -> bool {
+        self.form.is_some()
+    }
+
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        let event = match &mut self.form {
+            Some(form) => form.handle_key(key),
+            None => return,
+        };
+        match event {
+            FormEvent::Confirm => {
+                let form = self.form.take().unwrap();
+                self.add_field(form.value(0));
+            }
+            FormEvent::Cancel => self.form = None,
+            FormEvent::None => (),
+        }
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Plot draws numeric fields of the messages over time.".to_string(),
+            "A field is given by its topic followed by its path, e.g., /odom/twist/twist/linear/x."
+                .to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::NEXT => self.switch_plot(1),
+            input::PREVIOUS => self.switch_plot(-1),
+            input::ZOOM_IN => self.time_window = (self.time_window / 2.).max(MIN_TIME_WINDOW),
+            input::ZOOM_OUT => self.time_window = (self.time_window * 2.).min(MAX_TIME_WINDOW),
+            input::TOGGLE_AUTOSCALE => self.toggle_autoscale(),
+            input::PAUSE => self.toggle_pause(),
+            input::EDIT => {
+                self.form = Some(TextForm::new(
+                    "Add field",
+                    vec![("Field".to_string(), "/".to_string())],
+                ))
+            }
+            input::DELETE => self.remove_field(),
+            input::SAVE => self.save(),
+            _ => (),
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [input::NEXT.to_string(), "Shows the next plot".to_string()],
+            [
+                input::PREVIOUS.to_string(),
+                "Shows the previous plot".to_string(),
+            ],
+            [
+                input::ZOOM_IN.to_string(),
+                "Halves the time window".to_string(),
+            ],
+            [
+                input::ZOOM_OUT.to_string(),
+                "Doubles the time window".to_string(),
+            ],
+            [
+                input::TOGGLE_AUTOSCALE.to_string(),
+                "Enables/disables the automatic scaling of the y axis".to_string(),
+            ],
+            [
+                input::PAUSE.to_string(),
+                "Pauses/resumes the plot".to_string(),
+            ],
+            [
+                input::EDIT.to_string(),
+                "Adds a field to the plot".to_string(),
+            ],
+            [
+                input::DELETE.to_string(),
+                "Removes the last field of the plot".to_string(),
+            ],
+            [
+                input::SAVE.to_string(),
+                "Saves the plots to the user configuration".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Plot".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for Plot {
+    fn draw(&self, f: &mut Frame<B>) {
+        let name = match self.plots.get(self.current) {
+            Some(plot) => format!("Plot - {}", plot.name),
+            None => "Plot - no plot configured, add a field to create one".to_string(),
+        };
+        let title_text = vec![Spans::from(Span::styled(
+            name,
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let header = self.header();
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(header.len() as u16),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = Paragraph::new(header)
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+
+        let data = self.visible_data();
+        let y_bounds = if self.autoscale {
+            autoscale(&data)
+        } else {
+            self.y_bounds
+        };
+        let datasets: Vec<Dataset> = self
+            .series
+            .iter()
+            .zip(data.iter())
+            .enumerate()
+            .map(|(i, (series, points))| {
+                Dataset::default()
+                    .name(series.field.clone())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(COLORS[i % COLORS.len()]))
+                    .data(points)
+            })
+            .collect();
+        let x_labels = vec![
+            Span::raw(format!("{:.0}", -self.time_window)),
+            Span::raw(format!("{:.0}", -self.time_window / 2.)),
+            Span::raw("0"),
+        ];
+        let y_labels = vec![
+            Span::raw(format!("{:.3}", y_bounds[0])),
+            Span::raw(format!("{:.3}", (y_bounds[0] + y_bounds[1]) / 2.)),
+            Span::raw(format!("{:.3}", y_bounds[1])),
+        ];
+        let chart = Chart::new(datasets)
+            .block(Block::default().borders(Borders::ALL))
+            .x_axis(
+                Axis::default()
+                    .title("Time [s]")
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds([-self.time_window, 0.])
+                    .labels(x_labels),
+            )
+            .y_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds(y_bounds)
+                    .labels(y_labels),
+            );
+        f.render_widget(chart, areas[2]);
+
+        if let Some(form) = &self.form {
+            let area = f.size();
+            form.draw(f, area);
+        }
+    }
+}
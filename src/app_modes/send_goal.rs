@@ -0,0 +1,201 @@
+//! Send goal mode allows to send navigation goals to a move_base action server
+//! and to follow their execution.
+
+use crate::app_modes::ghost_pose::GhostPose;
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::GoalActionConfig;
+use crate::move_base::{self, MoveBaseClient};
+use rosrust_msg::actionlib_msgs::GoalStatus;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::Color;
+use tui::widgets::canvas::Context;
+use tui::Frame;
+
+/// Returns the color used to draw a goal with the given status.
+pub fn status_color(status: Option<u8>) -> Color {
+    match status {
+        None | Some(GoalStatus::PENDING) => Color::Yellow,
+        Some(GoalStatus::ACTIVE) => Color::Cyan,
+        Some(GoalStatus::SUCCEEDED) => Color::Green,
+        Some(GoalStatus::PREEMPTING) | Some(GoalStatus::RECALLING) => Color::Magenta,
+        _ => Color::Red,
+    }
+}
+
+/// Represents the send goal mode.
+pub struct SendGoal {
+    viewport: Rc<RefCell<Viewport>>,
+    ghost: GhostPose,
+    current_client: usize,
+    clients: Vec<MoveBaseClient>,
+}
+
+impl SendGoal {
+    pub fn new(actions: &Vec<GoalActionConfig>, viewport: Rc<RefCell<Viewport>>) -> SendGoal {
+        SendGoal {
+            ghost: GhostPose::new(viewport.clone()),
+            viewport: viewport,
+            current_client: 0,
+            clients: actions
+                .iter()
+                .map(|config| MoveBaseClient::new(&config.action))
+                .collect(),
+        }
+    }
+
+    fn send_goal(&mut self) {
+        if self.clients.is_empty() || !self.ghost.is_set() {
+            return;
+        }
+        let frame_id = self.viewport.borrow().static_frame.clone();
+        self.clients[self.current_client].send_goal(&self.ghost.pose, &frame_id);
+        self.ghost.active = false;
+    }
+
+    fn cancel_goal(&self) {
+        if !self.clients.is_empty() {
+            self.clients[self.current_client].cancel();
+        }
+    }
+}
+
+impl<B: Backend> BaseMode<B> for SendGoal {}
+
+impl AppMode for SendGoal {
+    fn run(&mut self) {
+        self.ghost.update();
+    }
+
+    fn reset(&mut self) {
+        self.ghost.reset();
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        if self.ghost.handle_input(input) || self.clients.is_empty() {
+            return;
+        }
+        match input.as_str() {
+            input::NEXT => self.current_client = (self.current_client + 1) % self.clients.len(),
+            input::PREVIOUS => {
+                self.current_client = if self.current_client > 0 {
+                    self.current_client - 1
+                } else {
+                    self.clients.len() - 1
+                };
+            }
+            input::CANCEL => self.reset(),
+            input::CANCEL_GOAL => self.cancel_goal(),
+            input::CONFIRM => self.send_goal(),
+            _ => (),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "Send Goal".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode allows to send navigation goals to a move_base action server.".to_string(),
+            "The top bar shows the selected action server and the status of the last goal."
+                .to_string(),
+            "The last goal is drawn on the map, colored according to its status.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = self.ghost.get_keymap();
+        keymap.extend(vec![
+            [
+                input::CANCEL.to_string(),
+                "Resets the desired pose.".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Sends the desired pose as a goal.".to_string(),
+            ],
+            [
+                input::CANCEL_GOAL.to_string(),
+                "Cancels the last sent goal.".to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Switches to the next action server.".to_string(),
+            ],
+            [
+                input::PREVIOUS.to_string(),
+                "Switches to the previous action server.".to_string(),
+            ],
+        ]);
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for SendGoal {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        self.viewport.borrow().draw_in_viewport(ctx);
+        if !self.clients.is_empty() {
+            let state = self.clients[self.current_client].get_state();
+            if let Some(target) = &state.target {
+                self.viewport
+                    .borrow()
+                    .draw_robot_outline(ctx, target, status_color(state.status));
+            }
+        }
+        self.ghost.draw(ctx);
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.ghost.bounds().0
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.ghost.bounds().1
+    }
+
+    fn info(&self) -> String {
+        if self.clients.is_empty() {
+            return "No action server configured!".to_string();
+        }
+        let client = &self.clients[self.current_client];
+        let state = client.get_state();
+        let mut info = format!("Action: /{}", client.action_ns);
+        if state.goal_id.is_some() {
+            let status = match state.status {
+                Some(status) => move_base::status_name(status),
+                None => "Sent",
+            };
+            info += &format!(", Goal: {}", status);
+            if !state.text.is_empty() {
+                info += &format!(" ({})", state.text);
+            }
+        }
+        match &state.feedback {
+            Some(feedback) if state.is_running() => {
+                info += &format!(
+                    ", Feedback: x: {:.2}, y: {:.2}, yaw: {:.2}",
+                    feedback.translation.x,
+                    feedback.translation.y,
+                    feedback.rotation.angle()
+                )
+            }
+            _ => (),
+        }
+        info += &format!(", Cursor step: {:.2}", self.ghost.increment);
+        let viewport_info = self.viewport.borrow().info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+    }
+}
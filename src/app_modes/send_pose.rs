@@ -1,187 +1,277 @@
 //! Send pose mode allows to send a pose on the given topic.
 
+use crate::app_modes::ghost_pose::GhostPose;
+use crate::app_modes::text_form::{FormEvent, TextForm};
 use crate::app_modes::viewport::{UseViewport, Viewport};
 use crate::app_modes::{input, AppMode, BaseMode};
-use crate::config::SendPoseConfig;
-use crate::footprint::get_current_footprint;
+use crate::config::{SendPoseConfig, Stamp};
 use crate::transformation;
-use approx::AbsDiffEq;
+use crossterm::event::KeyCode;
 use nalgebra::{Isometry2, Vector2};
 use std::cell::RefCell;
+use std::convert::TryInto;
 use std::rc::Rc;
 use tui::backend::Backend;
-use tui::style::Color;
-use tui::widgets::canvas::{Context, Line};
+use tui::layout::Rect;
+use tui::widgets::canvas::Context;
+use tui::Frame;
+
+/// Returns the 6x6 covariance matrix from the configured values.
+/// The configured values are either the full matrix, or the variances of x, y and yaw, which is
+/// checked when loading the config.
+fn get_covariance(values: &Vec<f64>) -> Vec<f64> {
+    if values.len() != 3 {
+        return values.clone();
+    }
+    let mut cov = vec![0.0; 36];
+    cov[0] = values[0];
+    cov[7] = values[1];
+    cov[35] = values[2];
+    cov
+}
 
 trait BasePosePubWrapper {
-    fn get_topic(&self) -> &String;
-    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, frame_id: String);
+    fn get_config(&self) -> &SendPoseConfig;
+    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, header: rosrust_msg::std_msgs::Header);
 }
 
 struct PosePubWrapper {
-    topic: String,
+    config: SendPoseConfig,
     publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::Pose>,
 }
 
 impl PosePubWrapper {
-    pub fn new(topic: &String) -> PosePubWrapper {
+    pub fn new(config: &SendPoseConfig) -> PosePubWrapper {
         PosePubWrapper {
-            topic: topic.clone(),
-            publisher: rosrust::publish(&topic, 1).unwrap(),
+            config: config.clone(),
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
         }
     }
 }
 
 impl BasePosePubWrapper for PosePubWrapper {
-    fn get_topic(&self) -> &String {
-        &self.topic
+    fn get_config(&self) -> &SendPoseConfig {
+        &self.config
     }
 
-    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, _frame_id: String) {
+    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, _header: rosrust_msg::std_msgs::Header) {
         self.publisher.send(msg).unwrap();
     }
 }
 
 struct PoseStampedPubWrapper {
-    topic: String,
+    config: SendPoseConfig,
     publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::PoseStamped>,
 }
 
 impl PoseStampedPubWrapper {
-    pub fn new(topic: &String) -> PoseStampedPubWrapper {
+    pub fn new(config: &SendPoseConfig) -> PoseStampedPubWrapper {
         PoseStampedPubWrapper {
-            topic: topic.clone(),
-            publisher: rosrust::publish(&topic, 1).unwrap(),
+            config: config.clone(),
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
         }
     }
 }
 
 impl BasePosePubWrapper for PoseStampedPubWrapper {
-    fn get_topic(&self) -> &String {
-        &self.topic
+    fn get_config(&self) -> &SendPoseConfig {
+        &self.config
     }
 
-    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, frame_id: String) {
+    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, header: rosrust_msg::std_msgs::Header) {
         let mut msg_stamped = rosrust_msg::geometry_msgs::PoseStamped::default();
-        msg_stamped.header.frame_id = frame_id;
-        msg_stamped.pose.orientation.x = msg.orientation.x;
-        msg_stamped.pose.orientation.y = msg.orientation.y;
-        msg_stamped.pose.orientation.z = msg.orientation.z;
-        msg_stamped.pose.orientation.w = msg.orientation.w;
-        msg_stamped.pose.position.x = msg.position.x;
-        msg_stamped.pose.position.y = msg.position.y;
-        msg_stamped.pose.position.z = 0.0;
+        msg_stamped.header = header;
+        msg_stamped.pose = msg;
         self.publisher.send(msg_stamped).unwrap();
     }
 }
 
 struct PoseCovPubWrapper {
-    topic: String,
+    config: SendPoseConfig,
     publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::PoseWithCovarianceStamped>,
 }
 
 impl PoseCovPubWrapper {
-    pub fn new(topic: &String) -> PoseCovPubWrapper {
+    pub fn new(config: &SendPoseConfig) -> PoseCovPubWrapper {
         PoseCovPubWrapper {
-            topic: topic.clone(),
-            publisher: rosrust::publish(&topic, 1).unwrap(),
+            config: config.clone(),
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
         }
     }
 }
 
 impl BasePosePubWrapper for PoseCovPubWrapper {
-    fn get_topic(&self) -> &String {
-        &self.topic
+    fn get_config(&self) -> &SendPoseConfig {
+        &self.config
     }
 
-    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, frame_id: String) {
+    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, header: rosrust_msg::std_msgs::Header) {
         let mut msg_cov = rosrust_msg::geometry_msgs::PoseWithCovarianceStamped::default();
-        msg_cov.header.frame_id = frame_id;
-        msg_cov.pose.pose.orientation.x = msg.orientation.x;
-        msg_cov.pose.pose.orientation.y = msg.orientation.y;
-        msg_cov.pose.pose.orientation.z = msg.orientation.z;
-        msg_cov.pose.pose.orientation.w = msg.orientation.w;
-        msg_cov.pose.pose.position.x = msg.position.x;
-        msg_cov.pose.pose.position.y = msg.position.y;
-        msg_cov.pose.pose.position.z = 0.0;
+        msg_cov.header = header;
+        msg_cov.pose.pose = msg;
+        msg_cov.pose.covariance = get_covariance(&self.config.covariance).try_into().unwrap();
         self.publisher.send(msg_cov).unwrap();
     }
 }
 
+struct PointStampedPubWrapper {
+    config: SendPoseConfig,
+    publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::PointStamped>,
+}
+
+impl PointStampedPubWrapper {
+    pub fn new(config: &SendPoseConfig) -> PointStampedPubWrapper {
+        PointStampedPubWrapper {
+            config: config.clone(),
+            publisher: rosrust::publish(&config.topic, 1).unwrap(),
+        }
+    }
+}
+
+impl BasePosePubWrapper for PointStampedPubWrapper {
+    fn get_config(&self) -> &SendPoseConfig {
+        &self.config
+    }
+
+    fn send(&self, msg: rosrust_msg::geometry_msgs::Pose, header: rosrust_msg::std_msgs::Header) {
+        let mut msg_point = rosrust_msg::geometry_msgs::PointStamped::default();
+        msg_point.header = header;
+        msg_point.point = msg.position;
+        self.publisher.send(msg_point).unwrap();
+    }
+}
+
 /// Represents the send pose mode.
 pub struct SendPose {
     viewport: Rc<RefCell<Viewport>>,
-    increment: f64,
-    robot_pose: Isometry2<f64>,
-    new_pose: Isometry2<f64>,
+    ghost: GhostPose,
     current_topic: usize,
     publishers: Vec<Box<dyn BasePosePubWrapper>>,
-    ghost_active: bool,
+    form: Option<TextForm>,
+    message: String,
 }
 
 impl SendPose {
     pub fn new(topics: &Vec<SendPoseConfig>, viewport: Rc<RefCell<Viewport>>) -> SendPose {
-        let base_link_pose = viewport.borrow().tf_listener.lookup_transform(
-            &viewport.borrow().static_frame,
-            &viewport.borrow().robot_frame,
-            rosrust::Time::new(),
-        );
-
-        let robot_pose = if base_link_pose.is_ok() {
-            transformation::ros_to_iso2d(&base_link_pose.unwrap().transform)
-        } else {
-            Isometry2::identity()
-        };
-
         let mut publishers = Vec::<Box<dyn BasePosePubWrapper>>::new();
 
         for topic in topics {
             match topic.msg_type.as_str() {
-                "Pose" => publishers.push(Box::new(PosePubWrapper::new(&topic.topic))),
-                "PoseStamped" => {
-                    publishers.push(Box::new(PoseStampedPubWrapper::new(&topic.topic)))
-                }
+                "Pose" => publishers.push(Box::new(PosePubWrapper::new(&topic))),
+                "PoseStamped" => publishers.push(Box::new(PoseStampedPubWrapper::new(&topic))),
                 "PoseWithCovarianceStamped" => {
-                    publishers.push(Box::new(PoseCovPubWrapper::new(&topic.topic)))
+                    publishers.push(Box::new(PoseCovPubWrapper::new(&topic)))
                 }
+                "PointStamped" => publishers.push(Box::new(PointStampedPubWrapper::new(&topic))),
                 _ => {}
             }
         }
 
         SendPose {
+            ghost: GhostPose::new(viewport.clone()),
             viewport: viewport,
             current_topic: 0,
             publishers: publishers,
-            increment: 0.1,
-            robot_pose: robot_pose.clone(),
-            new_pose: robot_pose,
-            ghost_active: false,
+            form: None,
+            message: "".to_string(),
         }
     }
 
-    fn move_new_pose(&mut self, x: f64, y: f64, yaw: f64) {
-        let new_yaw = self.new_pose.rotation.angle() + yaw;
-        let new_x = x * new_yaw.cos() - y * new_yaw.sin() + self.new_pose.translation.x;
-        let new_y = x * new_yaw.sin() + y * new_yaw.cos() + self.new_pose.translation.y;
-        self.new_pose = Isometry2::new(Vector2::new(new_x, new_y), new_yaw);
-        self.ghost_active = true;
+    fn open_form(&mut self) {
+        let pose = &self.ghost.pose;
+        self.form = Some(TextForm::new(
+            "Desired pose",
+            vec![
+                ("x".to_string(), format!("{:.3}", pose.translation.x)),
+                ("y".to_string(), format!("{:.3}", pose.translation.y)),
+                (
+                    "yaw".to_string(),
+                    format!("{:.2}", pose.rotation.angle().to_degrees()),
+                ),
+                ("yaw unit (deg/rad)".to_string(), "deg".to_string()),
+                (
+                    "frame".to_string(),
+                    self.viewport.borrow().static_frame.clone(),
+                ),
+            ],
+        ));
+    }
+
+    /// Reads the pose typed in the form and places the ghost there.
+    fn apply_form(&mut self, form: &TextForm) -> Result<(), String> {
+        let parse = |i: usize| {
+            form.value(i)
+                .parse::<f64>()
+                .map_err(|_e| format!("invalid {}: '{}'", form.fields[i].0, form.value(i)))
+        };
+        let x = parse(0)?;
+        let y = parse(1)?;
+        let yaw = match form.value(3) {
+            "deg" | "" => parse(2)?.to_radians(),
+            "rad" => parse(2)?,
+            unit => return Err(format!("invalid yaw unit: '{}'", unit)),
+        };
+        let frame = form.value(4).trim_start_matches('/');
+        let viewport = self.viewport.borrow();
+        let pose = Isometry2::new(Vector2::new(x, y), yaw);
+        let pose = if frame.is_empty() || frame == viewport.static_frame {
+            pose
+        } else {
+            let tf = viewport
+                .tf_listener
+                .lookup_transform(&viewport.static_frame, frame, rosrust::Time::new())
+                .map_err(|_e| {
+                    format!("no transform from {} to {}", frame, viewport.static_frame)
+                })?;
+            transformation::ros_to_iso2d(&tf.transform) * pose
+        };
+        drop(viewport);
+        self.ghost.set_pose(pose);
+        Ok(())
     }
 
     fn send_new_pose(&mut self) {
-        if self.new_pose.abs_diff_ne(&self.robot_pose, 0.01) {
-            let pose = transformation::iso2d_to_ros(&self.new_pose);
-            let frame_id = self.viewport.borrow().static_frame.to_string();
-            let mut msg = rosrust_msg::geometry_msgs::Pose::default();
-            msg.orientation.x = pose.rotation.x;
-            msg.orientation.y = pose.rotation.y;
-            msg.orientation.z = pose.rotation.z;
-            msg.orientation.w = pose.rotation.w;
-            msg.position.x = pose.translation.x;
-            msg.position.y = pose.translation.y;
-            msg.position.z = 0.0;
-            self.publishers[self.current_topic].send(msg, frame_id);
-            self.ghost_active = false;
+        if !self.ghost.is_set() {
+            return;
+        }
+        let publisher = &self.publishers[self.current_topic];
+        let config = publisher.get_config();
+        let viewport = self.viewport.borrow();
+
+        let mut pose = transformation::iso2d_to_ros_pose(&self.ghost.pose);
+        pose.position.z = config.z;
+        let mut header = rosrust_msg::std_msgs::Header::default();
+        header.frame_id = viewport.static_frame.clone();
+        if config.stamp == Stamp::Now {
+            header.stamp = rosrust::now();
         }
+
+        if !config.frame.is_empty() && config.frame != viewport.static_frame {
+            match viewport.tf_listener.lookup_transform(
+                &config.frame,
+                &viewport.static_frame,
+                rosrust::Time::new(),
+            ) {
+                Ok(tf) => {
+                    let iso = transformation::ros_transform_to_isometry(&tf.transform)
+                        * transformation::ros_pose_to_isometry(&pose);
+                    pose = transformation::iso3d_to_ros_pose(&iso);
+                    header.frame_id = config.frame.clone();
+                }
+                Err(_e) => {
+                    self.message = format!(
+                        "Error: no transform from {} to {}",
+                        viewport.static_frame, config.frame
+                    );
+                    return;
+                }
+            }
+        }
+
+        publisher.send(pose, header);
+        drop(viewport);
+        self.message = "".to_string();
+        self.ghost.active = false;
     }
 }
 
@@ -189,37 +279,42 @@ impl<B: Backend> BaseMode<B> for SendPose {}
 
 impl AppMode for SendPose {
     fn run(&mut self) {
-        let base_link_pose = self.viewport.borrow().tf_listener.lookup_transform(
-            &self.viewport.borrow().static_frame,
-            &self.viewport.borrow().robot_frame,
-            rosrust::Time::new(),
-        );
+        self.ghost.update();
+    }
+    fn reset(&mut self) {
+        self.ghost.reset();
+        self.form = None;
+        self.message = "".to_string();
+    }
 
-        self.robot_pose = if base_link_pose.is_ok() {
-            transformation::ros_to_iso2d(&base_link_pose.unwrap().transform)
-        } else {
-            Isometry2::identity()
-        };
+    fn is_editing(&self) -> bool {
+        self.form.is_some()
+    }
 
-        if !self.ghost_active {
-            self.new_pose = self.robot_pose.clone();
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        let event = match &mut self.form {
+            Some(form) => form.handle_key(key),
+            None => return,
+        };
+        match event {
+            FormEvent::Confirm => {
+                let form = self.form.take().unwrap();
+                self.message = match self.apply_form(&form) {
+                    Ok(_) => "".to_string(),
+                    Err(e) => format!("Error: {}", e),
+                };
+            }
+            FormEvent::Cancel => self.form = None,
+            FormEvent::None => (),
         }
     }
-    fn reset(&mut self) {
-        self.ghost_active = false;
-        self.run(); // Update the robot pose
-    }
+
     fn handle_input(&mut self, input: &String) {
         self.viewport.borrow_mut().handle_input(input);
+        if self.ghost.handle_input(input) {
+            return;
+        }
         match input.as_str() {
-            input::UP => self.move_new_pose(self.increment, 0.0, 0.0),
-            input::DOWN => self.move_new_pose(-self.increment, 0.0, 0.0),
-            input::LEFT => self.move_new_pose(0.0, self.increment, 0.0),
-            input::RIGHT => self.move_new_pose(0.0, -self.increment, 0.0),
-            input::ROTATE_LEFT => self.move_new_pose(0.0, 0.0, self.increment),
-            input::ROTATE_RIGHT => self.move_new_pose(0.0, 0.0, -self.increment),
-            input::INCREMENT_STEP => self.increment += 0.1,
-            input::DECREMENT_STEP => self.increment -= 0.1,
             input::NEXT => self.current_topic = (self.current_topic + 1) % self.publishers.len(),
             input::PREVIOUS => {
                 self.current_topic = if self.current_topic > 0 {
@@ -230,6 +325,7 @@ impl AppMode for SendPose {
             }
             input::CANCEL => self.reset(),
             input::CONFIRM => self.send_new_pose(),
+            input::EDIT => self.open_form(),
             _ => (),
         }
     }
@@ -248,31 +344,8 @@ impl AppMode for SendPose {
     }
 
     fn get_keymap(&self) -> Vec<[String; 2]> {
-        let mut keymap = vec![
-            [
-                input::UP.to_string(),
-                "Shifts the desired pose positively along the x axis.".to_string(),
-            ],
-            [
-                input::DOWN.to_string(),
-                "Shifts the desired pose negatively along the x axis.".to_string(),
-            ],
-            [
-                input::RIGHT.to_string(),
-                "Shifts the desired pose positively along the y axis.".to_string(),
-            ],
-            [
-                input::LEFT.to_string(),
-                "Shifts the desired pose negatively along the y axis.".to_string(),
-            ],
-            [
-                input::ROTATE_LEFT.to_string(),
-                "Rotates the desired pose counter-clockwise.".to_string(),
-            ],
-            [
-                input::ROTATE_RIGHT.to_string(),
-                "Rotates the desired pose clockwise.".to_string(),
-            ],
+        let mut keymap = self.ghost.get_keymap();
+        keymap.extend(vec![
             [
                 input::CANCEL.to_string(),
                 "Resets the desired pose.".to_string(),
@@ -282,12 +355,8 @@ impl AppMode for SendPose {
                 "Sends the desired pose.".to_string(),
             ],
             [
-                input::INCREMENT_STEP.to_string(),
-                "Increases the step size for manipulating the desired pose.".to_string(),
-            ],
-            [
-                input::DECREMENT_STEP.to_string(),
-                "Decreases the step size for manipulating the desired pose.".to_string(),
+                input::EDIT.to_string(),
+                "Opens a form to type the coordinates of the desired pose.".to_string(),
             ],
             [
                 input::NEXT.to_string(),
@@ -297,7 +366,7 @@ impl AppMode for SendPose {
                 input::PREVIOUS.to_string(),
                 "Switches to the previous topic to which the poses are sent.".to_string(),
             ],
-        ];
+        ]);
         keymap.extend(self.viewport.borrow().get_keymap());
         keymap
     }
@@ -306,54 +375,38 @@ impl AppMode for SendPose {
 impl UseViewport for SendPose {
     fn draw_in_viewport(&self, ctx: &mut Context) {
         self.viewport.borrow().draw_in_viewport(ctx);
-        if self.new_pose.abs_diff_ne(&self.robot_pose, 0.01) {
-            let pose_estimate_ros = transformation::iso2d_to_ros(&self.new_pose);
-            for elem in
-                &get_current_footprint(&pose_estimate_ros, &self.viewport.borrow().footprint)
-            {
-                ctx.draw(&Line {
-                    x1: elem.0,
-                    y1: elem.1,
-                    x2: elem.2,
-                    y2: elem.3,
-                    color: Color::Gray,
-                });
-            }
-            for mut line in
-                Viewport::get_frame_lines(&pose_estimate_ros, self.viewport.borrow().axis_length)
-            {
-                line.color = Color::Gray;
-                ctx.draw(&line);
-            }
-        }
+        self.ghost.draw(ctx);
     }
     fn x_bounds(&self) -> [f64; 2] {
-        let scale_factor = self.viewport.borrow().terminal_size.0 as f64
-            / self.viewport.borrow().terminal_size.1 as f64
-            * 0.5;
-        [
-            self.new_pose.translation.x
-                + self.viewport.borrow().initial_bounds[0] / self.viewport.borrow().zoom
-                    * scale_factor,
-            self.new_pose.translation.x
-                + self.viewport.borrow().initial_bounds[1] / self.viewport.borrow().zoom
-                    * scale_factor,
-        ]
+        self.ghost.bounds().0
     }
     fn y_bounds(&self) -> [f64; 2] {
-        [
-            self.new_pose.translation.y
-                + self.viewport.borrow().initial_bounds[2] / self.viewport.borrow().zoom,
-            self.new_pose.translation.y
-                + self.viewport.borrow().initial_bounds[3] / self.viewport.borrow().zoom,
-        ]
+        self.ghost.bounds().1
     }
 
     fn info(&self) -> String {
-        format!(
-            "Topic: /{}, Cursor step: {:.2}",
-            &self.publishers[self.current_topic].get_topic(),
-            &self.increment
-        )
+        let mut info = format!(
+            "Topic: /{}, Pose: x: {:.2}, y: {:.2}, yaw: {:.1}°, Cursor step: {:.2}",
+            &self.publishers[self.current_topic].get_config().topic,
+            self.ghost.pose.translation.x,
+            self.ghost.pose.translation.y,
+            self.ghost.pose.rotation.angle().to_degrees(),
+            &self.ghost.increment
+        );
+        if !self.message.is_empty() {
+            info += &format!(", {}", self.message);
+        }
+        let viewport_info = self.viewport.borrow().info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+        if let Some(form) = &self.form {
+            form.draw(f, area);
+        }
     }
 }
@@ -0,0 +1,454 @@
+use crate::app_modes::text_form::{FormEvent, TextForm};
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::config;
+use crate::dynamic::format_scalar;
+use crate::service::{self, ServiceDefinition};
+use crossterm::event::KeyCode;
+use rosrust::MsgValue;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use tui::Frame;
+
+/// State of the last service call, updated by the thread doing the call.
+#[derive(Default)]
+struct CallResult {
+    id: u64,
+    service: String,
+    srv_type: String,
+    pending: bool,
+    error: bool,
+    lines: Vec<String>,
+}
+
+/// Formats a response as one line per field, indented by depth.
+fn format_response(value: &MsgValue, name: &str, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match value {
+        MsgValue::Message(fields) => {
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            if !name.is_empty() {
+                lines.push(format!("{}{}:", indent, name));
+            }
+            for field in names {
+                let depth = if name.is_empty() { depth } else { depth + 1 };
+                format_response(&fields[field], field, depth, lines);
+            }
+        }
+        MsgValue::Array(items) => {
+            lines.push(format!("{}{}: [{}]", indent, name, items.len()));
+            for (i, item) in items.iter().enumerate() {
+                format_response(item, &i.to_string(), depth + 1, lines);
+            }
+        }
+        _ => lines.push(format!(
+            "{}{}: {}",
+            indent,
+            name,
+            format_scalar(value).unwrap_or_default()
+        )),
+    }
+}
+
+/// Definition of a service, loaded by the thread probing its type.
+struct ProbeResult {
+    id: u64,
+    service: String,
+    definition: Result<ServiceDefinition, String>,
+}
+
+pub struct ServiceCaller {
+    favourites: Vec<String>,
+    available: Vec<String>,
+    services: Vec<String>,
+    state: ListState,
+    form: Option<(String, ServiceDefinition, TextForm)>,
+    result: Arc<Mutex<CallResult>>,
+    probe_id: u64,
+    probe: Arc<Mutex<Option<ProbeResult>>>,
+    message: String,
+}
+
+impl ServiceCaller {
+    pub fn new(favourites: Vec<String>) -> ServiceCaller {
+        ServiceCaller {
+            favourites,
+            available: Vec::new(),
+            services: Vec::new(),
+            state: ListState::default(),
+            form: None,
+            result: Arc::new(Mutex::new(CallResult::default())),
+            probe_id: 0,
+            probe: Arc::new(Mutex::new(None)),
+            message: "".to_string(),
+        }
+    }
+
+    /// Lists the favourite services first, then the other available ones.
+    fn update_services(&mut self) {
+        self.available = match service::list_services() {
+            Ok(services) => services,
+            Err(e) => {
+                self.message = format!("Error: cannot list the services: {}", e);
+                Vec::new()
+            }
+        };
+        self.services = self.favourites.clone();
+        for service in self.available.iter() {
+            if !self.favourites.contains(service) {
+                self.services.push(service.clone());
+            }
+        }
+        let selected = match self.state.selected() {
+            _ if self.services.is_empty() => None,
+            Some(i) => Some(i.min(self.services.len() - 1)),
+            None => Some(0),
+        };
+        self.state.select(selected);
+    }
+
+    fn select(&mut self, offset: i64) {
+        if self.services.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => (i as i64 + offset).rem_euclid(self.services.len() as i64) as usize,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    fn selected_service(&self) -> Option<String> {
+        self.state
+            .selected()
+            .and_then(|i| self.services.get(i))
+            .cloned()
+    }
+
+    fn toggle_favourite(&mut self) {
+        let service = match self.selected_service() {
+            Some(service) => service,
+            None => return,
+        };
+        match self.favourites.iter().position(|s| *s == service) {
+            Some(i) => {
+                self.favourites.remove(i);
+            }
+            None => self.favourites.push(service),
+        }
+        let favourites = self.favourites.clone();
+        if let Err(e) = config::update_user_config(|cfg| cfg.favourite_services = favourites) {
+            self.message = format!("Error: cannot save the favourites: {}", e);
+        }
+        self.update_services();
+    }
+
+    /// Loads the definition of the selected service in a separate thread, as probing its type
+    /// may take until the timeout. The call is prepared once the definition is loaded.
+    fn prepare_call(&mut self) {
+        let service = match self.selected_service() {
+            Some(service) => service,
+            None => return,
+        };
+        self.probe_id += 1;
+        let id = self.probe_id;
+        self.message = format!("Loading the type of {}...", service);
+        let probe = self.probe.clone();
+        thread::spawn(move || {
+            let definition =
+                service::service_type(&service).and_then(|t| ServiceDefinition::load(&t));
+            *probe.lock().unwrap() = Some(ProbeResult {
+                id,
+                service,
+                definition,
+            });
+        });
+    }
+
+    /// Calls the service directly if the request is empty, otherwise opens a form to type the
+    /// request.
+    fn start_call(&mut self, service: String, definition: Result<ServiceDefinition, String>) {
+        let definition = match definition {
+            Ok(definition) => definition,
+            Err(e) => {
+                self.message = format!("Error: {}: {}", service, e);
+                return;
+            }
+        };
+        self.message = "".to_string();
+        if definition.request_fields.is_empty() {
+            self.call(service, definition, HashMap::new());
+            return;
+        }
+        let mut fields = Vec::new();
+        for (field_type, name) in definition.request_fields.iter() {
+            let default = match field_type.as_str() {
+                "bool" => "false",
+                "string" => "",
+                _ => "0",
+            };
+            if let Err(e) = service::parse_field(field_type, default) {
+                self.message = format!("Error: {}: {}", definition.srv_type, e);
+                return;
+            }
+            fields.push((format!("{} ({})", name, field_type), default.to_string()));
+        }
+        let form = TextForm::new(&format!("Call {}", service), fields);
+        self.form = Some((service, definition, form));
+    }
+
+    fn apply_form(&mut self, service: String, definition: ServiceDefinition, form: &TextForm) {
+        let mut request = HashMap::new();
+        for (i, (field_type, name)) in definition.request_fields.iter().enumerate() {
+            match service::parse_field(field_type, form.value(i)) {
+                Ok(value) => {
+                    request.insert(name.clone(), value);
+                }
+                Err(e) => {
+                    self.message = format!("Error: {}", e);
+                    return;
+                }
+            }
+        }
+        self.call(service, definition, request);
+    }
+
+    /// Calls the service in a separate thread, the response is shown when received.
+    fn call(
+        &mut self,
+        service: String,
+        definition: ServiceDefinition,
+        request: HashMap<String, MsgValue>,
+    ) {
+        let id = {
+            let mut result = self.result.lock().unwrap();
+            *result = CallResult {
+                id: result.id + 1,
+                service: service.clone(),
+                srv_type: definition.srv_type.clone(),
+                pending: true,
+                error: false,
+                lines: Vec::new(),
+            };
+            result.id
+        };
+        let result = self.result.clone();
+        thread::spawn(move || {
+            let response = service::call(&service, &definition, &request);
+            let mut result = result.lock().unwrap();
+            if result.id != id {
+                return; // Another call was started in the meantime
+            }
+            result.pending = false;
+            match response {
+                Ok(value) => {
+                    format_response(&value, "", 0, &mut result.lines);
+                    if result.lines.is_empty() {
+                        result.lines.push("Empty response".to_string());
+                    }
+                }
+                Err(e) => {
+                    result.error = true;
+                    result.lines.push(e);
+                }
+            }
+        });
+    }
+
+    fn response(&self) -> Vec<Spans> {
+        let result = self.result.lock().unwrap();
+        if result.service.is_empty() {
+            return vec![Spans::from(Span::raw("No service called yet"))];
+        }
+        let mut spans = vec![Spans::from(vec![
+            Span::styled(
+                result.service.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" ({})", result.srv_type)),
+        ])];
+        if result.pending {
+            spans.push(Spans::from(Span::styled(
+                "Waiting for the response...",
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        let style = if result.error {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        spans.extend(
+            result
+                .lines
+                .iter()
+                .map(|line| Spans::from(Span::styled(line.clone(), style))),
+        );
+        spans
+    }
+}
+
+impl<B: Backend> BaseMode<B> for ServiceCaller {}
+
+impl AppMode for ServiceCaller {
+    fn run(&mut self) {
+        let probe = match self.probe.lock().unwrap().take() {
+            Some(probe) => probe,
+            None => return,
+        };
+        // Only the last service selected is called
+        if probe.id == self.probe_id {
+            self.start_call(probe.service, probe.definition);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.probe_id += 1;
+        self.form = None;
+        self.message = "".to_string();
+        self.update_services();
+    }
+
+    fn is_editing(&self) -> bool {
+        self.form.is_some()
+    }
+
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        let event = match &mut self.form {
+            Some((_, _, form)) => form.handle_key(key),
+            None => return,
+        };
+        match event {
+            FormEvent::Confirm => {
+                let (service, definition, form) = self.form.take().unwrap();
+                self.apply_form(service, definition, &form);
+            }
+            FormEvent::Cancel => self.form = None,
+            FormEvent::None => (),
+        }
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Service caller calls the available services and shows their response.".to_string(),
+            "The favourite services are listed first.".to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        match input.as_str() {
+            input::UP => self.select(-1),
+            input::DOWN => self.select(1),
+            input::CONFIRM => self.prepare_call(),
+            input::SAVE => self.toggle_favourite(),
+            input::NEXT => {
+                self.message = "".to_string();
+                self.update_services();
+            }
+            _ => (),
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Selects the previous service".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Selects the next service".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Calls the selected service, asking for the request if not empty".to_string(),
+            ],
+            [
+                input::SAVE.to_string(),
+                "Adds/removes the selected service to/from the favourites".to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Refreshes the service list".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Service caller".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for ServiceCaller {
+    fn draw(&self, f: &mut Frame<B>) {
+        let title_text = vec![Spans::from(Span::styled(
+            "Service caller",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .horizontal_margin(20)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(2),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(areas[2]);
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = Paragraph::new(Spans::from(Span::raw(self.message.clone())))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let items: Vec<ListItem> = self
+            .services
+            .iter()
+            .map(|service| {
+                let text = if self.favourites.contains(service) {
+                    format!("★ {}", service)
+                } else {
+                    format!("  {}", service)
+                };
+                // Favourites that are not advertised at the moment
+                let style = if self.available.contains(service) {
+                    Style::default()
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                ListItem::new(text).style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().title("Services").borders(Borders::ALL))
+            .highlight_symbol(">> ");
+        let response = Paragraph::new(self.response())
+            .block(Block::default().title("Response").borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+        f.render_stateful_widget(list, columns[0], &mut self.state.clone());
+        f.render_widget(response, columns[1]);
+
+        if let Some((_, _, form)) = &self.form {
+            let area = f.size();
+            form.draw(f, area);
+        }
+    }
+}
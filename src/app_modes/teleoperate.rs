@@ -1,45 +1,292 @@
 use crate::app_modes::viewport::{UseViewport, Viewport};
 use crate::app_modes::{input, AppMode, BaseMode};
-use crate::config::TeleopConfig;
+#[cfg(feature = "ackermann")]
+use crate::config::{AckermannConfig, Steering};
+use crate::config::{JoyConfig, SpeedProfile, TeleopConfig, TeleopMsgType};
+use crate::footprint::{get_footprint_radius, point_in_polygon};
+use crate::transformation;
+use nalgebra::{Isometry2, Point2, Vector2};
 use rosrust;
 use rosrust_msg;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
 use tui::widgets::canvas::Context;
+use tui::widgets::{Block, Borders, Clear, Gauge};
+use tui::Frame;
 
 pub struct Teleoperate {
     viewport: Rc<RefCell<Viewport>>,
     current_velocities: Velocities,
-    cmd_vel_pub: rosrust::Publisher<rosrust_msg::geometry_msgs::Twist>,
-    increment: f64,
+    target_velocities: Velocities,
+    cmd_vel_pub: Box<dyn BaseCmdPubWrapper>,
+    frame_id: String,
+    linear_increment: f64,
+    angular_increment: f64,
     increment_step: f64,
+    angular_increment_step: f64,
+    holonomic: bool,
+    speed_profiles: Vec<SpeedProfile>,
+    current_profile: usize,
     publish_cmd_vel_when_idle: bool,
     has_published_zero_once: bool,
+    max_linear_velocity: f64,
+    max_angular_velocity: f64,
+    max_linear_acceleration: f64,
+    max_angular_acceleration: f64,
+    deadman_timeout: Option<Duration>,
+    last_input: Instant,
+    last_tick: Instant,
+    preview_time: f64,
+    block_collisions: bool,
+    preview: Vec<Isometry2<f64>>,
+    collision: bool,
+    blocked: bool, // The preview of the blocked command is kept until the next movement key
 }
 
+/// Time step used to simulate the trajectory of the robot.
+const PREVIEW_STEP: f64 = 0.1;
+/// Every how many simulation steps the footprint is drawn.
+const PREVIEW_DRAW_EVERY: usize = 5;
+/// Index of the normal profile among the preset ones.
+const PRESET_START_PROFILE: usize = 1;
+
+/// Slow, normal and fast profiles, used if none is configured. The normal one uses the default
+/// increments and the maximum velocities, the slow one halves them, and the fast one doubles the
+/// increments.
+fn preset_profiles(config: &TeleopConfig) -> Vec<SpeedProfile> {
+    let profile = |name: &str, increment_scale: f64, velocity_scale: f64| SpeedProfile {
+        name: name.to_string(),
+        linear_increment: config.default_increment * increment_scale,
+        angular_increment: config.default_angular_increment * increment_scale,
+        max_linear_velocity: config.max_linear_velocity * velocity_scale,
+        max_angular_velocity: config.max_angular_velocity * velocity_scale,
+    };
+    vec![
+        profile("slow", 0.5, 0.5),
+        profile("normal", 1.0, 1.0),
+        profile("fast", 2.0, 1.0),
+    ]
+}
+
+#[derive(Clone, Default)]
 pub struct Velocities {
     x: f64,
     y: f64,
     theta: f64,
 }
 
+impl Velocities {
+    fn is_zero(&self) -> bool {
+        self.x == 0 as f64 && self.y == 0 as f64 && self.theta == 0 as f64
+    }
+}
+
+/// Moves `current` towards `target` by at most `max_step`.
+fn ramp(current: f64, target: f64, max_step: f64) -> f64 {
+    current + (target - current).max(-max_step).min(max_step)
+}
+
+/// Scales `value` to the range [-1, 1], given its maximum magnitude.
+fn normalize(value: f64, max: f64) -> f64 {
+    if max > 0.0 {
+        (value / max).max(-1.0).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+trait BaseCmdPubWrapper {
+    fn send(&self, vel: &Velocities, header: rosrust_msg::std_msgs::Header);
+}
+
+struct TwistPubWrapper {
+    publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::Twist>,
+}
+
+impl TwistPubWrapper {
+    pub fn new(topic: &String) -> TwistPubWrapper {
+        TwistPubWrapper {
+            publisher: rosrust::publish(topic, 1).unwrap(),
+        }
+    }
+}
+
+impl BaseCmdPubWrapper for TwistPubWrapper {
+    fn send(&self, vel: &Velocities, _header: rosrust_msg::std_msgs::Header) {
+        let mut vel_cmd = rosrust_msg::geometry_msgs::Twist::default();
+        vel_cmd.linear.x = vel.x;
+        vel_cmd.linear.y = vel.y;
+        vel_cmd.angular.z = vel.theta;
+        self.publisher.send(vel_cmd).unwrap();
+    }
+}
+
+struct TwistStampedPubWrapper {
+    publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::TwistStamped>,
+}
+
+impl TwistStampedPubWrapper {
+    pub fn new(topic: &String) -> TwistStampedPubWrapper {
+        TwistStampedPubWrapper {
+            publisher: rosrust::publish(topic, 1).unwrap(),
+        }
+    }
+}
+
+impl BaseCmdPubWrapper for TwistStampedPubWrapper {
+    fn send(&self, vel: &Velocities, header: rosrust_msg::std_msgs::Header) {
+        let mut vel_cmd = rosrust_msg::geometry_msgs::TwistStamped::default();
+        vel_cmd.header = header;
+        vel_cmd.twist.linear.x = vel.x;
+        vel_cmd.twist.linear.y = vel.y;
+        vel_cmd.twist.angular.z = vel.theta;
+        self.publisher.send(vel_cmd).unwrap();
+    }
+}
+
+#[cfg(feature = "ackermann")]
+struct AckermannPubWrapper {
+    config: AckermannConfig,
+    max_linear_velocity: f64,
+    max_angular_velocity: f64,
+    publisher: rosrust::Publisher<rosrust_msg::ackermann_msgs::AckermannDriveStamped>,
+}
+
+#[cfg(feature = "ackermann")]
+impl AckermannPubWrapper {
+    pub fn new(config: &TeleopConfig) -> AckermannPubWrapper {
+        AckermannPubWrapper {
+            config: config.ackermann.clone(),
+            max_linear_velocity: config.max_linear_velocity,
+            max_angular_velocity: config.max_angular_velocity,
+            publisher: rosrust::publish(&config.cmd_vel_topic, 1).unwrap(),
+        }
+    }
+}
+
+#[cfg(feature = "ackermann")]
+impl BaseCmdPubWrapper for AckermannPubWrapper {
+    fn send(&self, vel: &Velocities, header: rosrust_msg::std_msgs::Header) {
+        // The steering angle is proportional to the velocity of the keys used for steering
+        let steering = match self.config.steering {
+            Steering::Lateral => normalize(vel.y, self.max_linear_velocity),
+            Steering::Rotation => normalize(vel.theta, self.max_angular_velocity),
+        };
+        let mut drive_cmd = rosrust_msg::ackermann_msgs::AckermannDriveStamped::default();
+        drive_cmd.header = header;
+        drive_cmd.drive.speed = vel.x as f32;
+        drive_cmd.drive.steering_angle = (steering * self.config.max_steering_angle) as f32;
+        self.publisher.send(drive_cmd).unwrap();
+    }
+}
+
+struct JoyPubWrapper {
+    config: JoyConfig,
+    max_linear_velocity: f64,
+    max_angular_velocity: f64,
+    publisher: rosrust::Publisher<rosrust_msg::sensor_msgs::Joy>,
+}
+
+impl JoyPubWrapper {
+    pub fn new(config: &TeleopConfig) -> JoyPubWrapper {
+        JoyPubWrapper {
+            config: config.joy.clone(),
+            max_linear_velocity: config.max_linear_velocity,
+            max_angular_velocity: config.max_angular_velocity,
+            publisher: rosrust::publish(&config.cmd_vel_topic, 1).unwrap(),
+        }
+    }
+}
+
+impl BaseCmdPubWrapper for JoyPubWrapper {
+    fn send(&self, vel: &Velocities, header: rosrust_msg::std_msgs::Header) {
+        let mut joy = rosrust_msg::sensor_msgs::Joy::default();
+        joy.header = header;
+        joy.axes = vec![0.0; self.config.num_axes];
+        joy.buttons = vec![0; self.config.num_buttons];
+        let axes = [
+            (
+                self.config.axis_x,
+                normalize(vel.x, self.max_linear_velocity),
+            ),
+            (
+                self.config.axis_y,
+                normalize(vel.y, self.max_linear_velocity),
+            ),
+            (
+                self.config.axis_theta,
+                normalize(vel.theta, self.max_angular_velocity),
+            ),
+        ];
+        for (axis, value) in axes.iter() {
+            if *axis >= 0 && (*axis as usize) < joy.axes.len() {
+                joy.axes[*axis as usize] = *value as f32;
+            }
+        }
+        let button = self.config.enable_button;
+        if button >= 0 && (button as usize) < joy.buttons.len() && !vel.is_zero() {
+            joy.buttons[button as usize] = 1;
+        }
+        self.publisher.send(joy).unwrap();
+    }
+}
+
 impl Teleoperate {
     pub fn new(viewport: Rc<RefCell<Viewport>>, config: TeleopConfig) -> Teleoperate {
-        let cmd_vel_publisher = rosrust::publish(&config.cmd_vel_topic, 1).unwrap();
-        let initial_velocities = Velocities {
-            x: 0.,
-            y: 0.,
-            theta: 0.,
+        let cmd_vel_publisher: Box<dyn BaseCmdPubWrapper> = match config.msg_type {
+            TeleopMsgType::Twist => Box::new(TwistPubWrapper::new(&config.cmd_vel_topic)),
+            TeleopMsgType::TwistStamped => {
+                Box::new(TwistStampedPubWrapper::new(&config.cmd_vel_topic))
+            }
+            #[cfg(feature = "ackermann")]
+            TeleopMsgType::AckermannDriveStamped => Box::new(AckermannPubWrapper::new(&config)),
+            TeleopMsgType::Joy => Box::new(JoyPubWrapper::new(&config)),
+        };
+        let (speed_profiles, current_profile) = if config.speed_profiles.is_empty() {
+            (preset_profiles(&config), PRESET_START_PROFILE)
+        } else {
+            (config.speed_profiles.clone(), 0)
+        };
+        let frame_id = if config.frame_id.is_empty() {
+            viewport.borrow().robot_frame.clone()
+        } else {
+            config.frame_id.clone()
         };
         Teleoperate {
             viewport: viewport,
             cmd_vel_pub: cmd_vel_publisher,
-            current_velocities: initial_velocities,
-            increment: config.default_increment,
+            frame_id: frame_id,
+            current_velocities: Velocities::default(),
+            target_velocities: Velocities::default(),
+            linear_increment: speed_profiles[current_profile].linear_increment,
+            angular_increment: speed_profiles[current_profile].angular_increment,
             increment_step: config.increment_step,
+            angular_increment_step: config.angular_increment_step,
+            holonomic: config.holonomic,
+            speed_profiles: speed_profiles,
+            current_profile: current_profile,
             publish_cmd_vel_when_idle: config.publish_cmd_vel_when_idle,
             has_published_zero_once: true, // Initialize to true so the robot is not stopped when entering the mode
+            max_linear_velocity: config.max_linear_velocity,
+            max_angular_velocity: config.max_angular_velocity,
+            max_linear_acceleration: config.max_linear_acceleration,
+            max_angular_acceleration: config.max_angular_acceleration,
+            deadman_timeout: if config.deadman_timeout > 0.0 {
+                Some(Duration::from_secs_f64(config.deadman_timeout))
+            } else {
+                None
+            },
+            last_input: Instant::now(),
+            last_tick: Instant::now(),
+            preview_time: config.collision_preview_time,
+            block_collisions: config.block_collisions,
+            preview: Vec::new(),
+            collision: false,
+            blocked: false,
         }
     }
 }
@@ -48,43 +295,177 @@ impl<B: Backend> BaseMode<B> for Teleoperate {}
 
 impl Teleoperate {
     fn publish_current_cmd_val(&mut self) {
-        let mut vel_cmd = rosrust_msg::geometry_msgs::Twist::default();
-        vel_cmd.linear.x = self.current_velocities.x;
-        vel_cmd.linear.y = self.current_velocities.y;
-        vel_cmd.angular.z = self.current_velocities.theta;
-        self.cmd_vel_pub.send(vel_cmd).unwrap();
+        let mut header = rosrust_msg::std_msgs::Header::default();
+        header.frame_id = self.frame_id.clone();
+        header.stamp = rosrust::now();
+        self.cmd_vel_pub.send(&self.current_velocities, header);
+    }
+
+    /// Returns the maximum linear and angular velocities of the current speed profile.
+    fn profile_limits(&self) -> (f64, f64) {
+        let profile = &self.speed_profiles[self.current_profile];
+        (
+            profile.max_linear_velocity.min(self.max_linear_velocity),
+            profile.max_angular_velocity.min(self.max_angular_velocity),
+        )
+    }
+
+    fn switch_profile(&mut self, offset: i64) {
+        let n = self.speed_profiles.len() as i64;
+        self.current_profile = ((self.current_profile as i64 + offset).rem_euclid(n)) as usize;
+        let profile = &self.speed_profiles[self.current_profile];
+        self.linear_increment = profile.linear_increment;
+        self.angular_increment = profile.angular_increment;
+        self.change_target(0.0, 0.0, 0.0); // Clamp to the new limits
+    }
+
+    fn change_target(&mut self, x: f64, y: f64, theta: f64) {
+        self.blocked = false;
+        let (max_lin, max_ang) = self.profile_limits();
+        let max_lat = if self.holonomic { max_lin } else { 0.0 };
+        let target = &mut self.target_velocities;
+        target.x = (target.x + x).max(-max_lin).min(max_lin);
+        target.y = (target.y + y).max(-max_lat).min(max_lat);
+        target.theta = (target.theta + theta).max(-max_ang).min(max_ang);
+    }
+
+    /// Moves the current velocities towards the target ones, respecting the acceleration limits.
+    fn update_velocities(&mut self, dt: f64) {
+        if let Some(timeout) = self.deadman_timeout {
+            if self.last_input.elapsed() > timeout {
+                self.target_velocities = Velocities::default();
+            }
+        }
+        let max_lin_step = self.max_linear_acceleration * dt;
+        let max_ang_step = self.max_angular_acceleration * dt;
+        let current = &mut self.current_velocities;
+        let target = &self.target_velocities;
+        current.x = ramp(current.x, target.x, max_lin_step);
+        current.y = ramp(current.y, target.y, max_lin_step);
+        current.theta = ramp(current.theta, target.theta, max_ang_step);
+    }
+}
+
+impl Teleoperate {
+    /// Simulates the robot moving with the target velocities for the preview time,
+    /// and checks whether the footprint hits any laser point or occupied map cell.
+    fn update_preview(&mut self) {
+        self.preview.clear();
+        self.collision = false;
+        if self.preview_time <= 0.0 || self.target_velocities.is_zero() {
+            return;
+        }
+        let viewport = self.viewport.borrow();
+        let mut pose = match viewport.tf_listener.lookup_transform(
+            &viewport.static_frame,
+            &viewport.robot_frame,
+            rosrust::Time::new(),
+        ) {
+            Ok(tf) => transformation::ros_to_iso2d(&tf.transform),
+            Err(_e) => return,
+        };
+        let step = Isometry2::new(
+            Vector2::new(
+                self.target_velocities.x * PREVIEW_STEP,
+                self.target_velocities.y * PREVIEW_STEP,
+            ),
+            self.target_velocities.theta * PREVIEW_STEP,
+        );
+        let start = pose;
+        let origin = pose.translation.vector;
+        let n_steps = (self.preview_time / PREVIEW_STEP).ceil() as usize;
+        for _ in 0..n_steps {
+            pose = pose * step;
+            self.preview.push(pose);
+        }
+
+        // Only check the obstacles that can be reached during the preview
+        let reach = self
+            .preview
+            .iter()
+            .map(|p| (p.translation.vector - origin).norm())
+            .fold(0.0, f64::max)
+            + get_footprint_radius(&viewport.footprint)
+            + PREVIEW_STEP;
+        let mut obstacles: Vec<(f64, f64)> = Vec::new();
+        let mut collect = |points: &Vec<(f64, f64)>| {
+            obstacles.extend(
+                points
+                    .iter()
+                    .filter(|pt| (Vector2::new(pt.0, pt.1) - origin).norm() <= reach),
+            );
+        };
+        for laser in &viewport.listeners.lasers {
+            collect(&*laser.points.read().unwrap());
+        }
+        for map in &viewport.listeners.maps {
+            collect(&*map.points.read().unwrap());
+        }
+
+        let hits = |pose: &Isometry2<f64>, pt: &(f64, f64)| {
+            let local = pose.inverse() * Point2::new(pt.0, pt.1);
+            point_in_polygon((local.x, local.y), &viewport.footprint)
+        };
+        // Obstacles already inside the footprint, e.g., when touching a wall, would block any
+        // command, including moving away, so only the ones newly hit are collisions
+        obstacles.retain(|pt| !hits(&start, pt));
+        self.collision = self
+            .preview
+            .iter()
+            .any(|p| obstacles.iter().any(|pt| hits(p, pt)));
     }
 }
 
 impl AppMode for Teleoperate {
     fn handle_input(&mut self, input: &String) {
         self.viewport.borrow_mut().handle_input(input);
+        self.last_input = Instant::now();
         match input.as_str() {
-            input::UP => self.current_velocities.x += 1 as f64 * self.increment,
-            input::DOWN => self.current_velocities.x += -1 as f64 * self.increment,
-            input::LEFT => self.current_velocities.y += 1 as f64 * self.increment,
-            input::RIGHT => self.current_velocities.y -= 1 as f64 * self.increment,
-            input::ROTATE_LEFT => self.current_velocities.theta += 1 as f64 * self.increment,
-            input::ROTATE_RIGHT => self.current_velocities.theta += -1 as f64 * self.increment,
-            input::INCREMENT_STEP => self.increment += self.increment_step,
+            input::UP => self.change_target(self.linear_increment, 0.0, 0.0),
+            input::DOWN => self.change_target(-self.linear_increment, 0.0, 0.0),
+            input::LEFT if self.holonomic => self.change_target(0.0, self.linear_increment, 0.0),
+            input::RIGHT if self.holonomic => self.change_target(0.0, -self.linear_increment, 0.0),
+            input::ROTATE_LEFT | input::LEFT => {
+                self.change_target(0.0, 0.0, self.angular_increment)
+            }
+            input::ROTATE_RIGHT | input::RIGHT => {
+                self.change_target(0.0, 0.0, -self.angular_increment)
+            }
+            input::INCREMENT_STEP => {
+                self.linear_increment += self.increment_step;
+                self.angular_increment += self.angular_increment_step;
+            }
             input::DECREMENT_STEP => {
-                self.increment = self
+                self.linear_increment = self
                     .increment_step
-                    .max(self.increment - self.increment_step)
+                    .max(self.linear_increment - self.increment_step);
+                self.angular_increment = self
+                    .angular_increment_step
+                    .max(self.angular_increment - self.angular_increment_step);
             }
+            input::NEXT => self.switch_profile(1),
+            input::PREVIOUS => self.switch_profile(-1),
             _ => self.reset(),
         }
     }
 
     fn run(&mut self) {
+        let dt = self.last_tick.elapsed().as_secs_f64();
+        self.last_tick = Instant::now();
+        if !self.blocked {
+            self.update_preview();
+        }
+        if self.collision && self.block_collisions {
+            self.blocked = true;
+            self.target_velocities = Velocities::default();
+            self.current_velocities = Velocities::default();
+        }
+        self.update_velocities(dt);
+
         // If the velocity is reset to 0 only publish it once
         // this prevents the robot from being blocked if the
         // app mode is not closed
-        if !self.publish_cmd_vel_when_idle
-            && self.current_velocities.x == 0 as f64
-            && self.current_velocities.y == 0 as f64
-            && self.current_velocities.theta == 0 as f64
-        {
+        if !self.publish_cmd_vel_when_idle && self.current_velocities.is_zero() {
             // If we did not publish the stop, do it once
             if !self.has_published_zero_once {
                 self.has_published_zero_once = true;
@@ -98,11 +479,11 @@ impl AppMode for Teleoperate {
     }
 
     fn reset(&mut self) {
-        self.current_velocities = Velocities {
-            x: 0.,
-            y: 0.,
-            theta: 0.,
-        };
+        // Stopping does not respect the acceleration limits
+        self.blocked = false;
+        self.target_velocities = Velocities::default();
+        self.current_velocities = Velocities::default();
+        self.last_tick = Instant::now();
         self.run(); // Send 0 velocities just in case
     }
 
@@ -112,7 +493,11 @@ impl AppMode for Teleoperate {
 
     fn get_description(&self) -> Vec<String> {
         vec!["This mode allows to teleoperate the robot by publishing velocity commands on the given topic.".to_string(),
-        "The viewport is centered on the robot.".to_string()]
+        "The commands are sent as Twist, TwistStamped, AckermannDriveStamped or Joy messages, depending on the configuration.".to_string(),
+        "The viewport is centered on the robot.".to_string(),
+        "The velocities are limited and ramped according to the configured limits.".to_string(),
+        "If no key is pressed for the configured timeout, the robot is stopped.".to_string(),
+        "The predicted footprint of the robot is shown in red if it would hit an obstacle.".to_string()]
     }
 
     fn get_keymap(&self) -> Vec<[String; 2]> {
@@ -125,14 +510,25 @@ impl AppMode for Teleoperate {
                 input::DOWN.to_string(),
                 "Moves negatively along the x axis.".to_string(),
             ],
-            [
-                input::RIGHT.to_string(),
-                "Moves positively along the y axis.".to_string(),
-            ],
-            [
-                input::LEFT.to_string(),
-                "Moves negatively along the y axis.".to_string(),
-            ],
+            if self.holonomic {
+                [
+                    input::RIGHT.to_string(),
+                    "Moves negatively along the y axis.".to_string(),
+                ]
+            } else {
+                [input::RIGHT.to_string(), "Rotates clockwise.".to_string()]
+            },
+            if self.holonomic {
+                [
+                    input::LEFT.to_string(),
+                    "Moves positively along the y axis.".to_string(),
+                ]
+            } else {
+                [
+                    input::LEFT.to_string(),
+                    "Rotates counter-clockwise.".to_string(),
+                ]
+            },
             [
                 input::ROTATE_LEFT.to_string(),
                 "Rotates counter-clockwise.".to_string(),
@@ -149,6 +545,14 @@ impl AppMode for Teleoperate {
                 input::DECREMENT_STEP.to_string(),
                 "Decreases the velocity step.".to_string(),
             ],
+            [
+                input::NEXT.to_string(),
+                "Switches to the next speed profile.".to_string(),
+            ],
+            [
+                input::PREVIOUS.to_string(),
+                "Switches to the previous speed profile.".to_string(),
+            ],
         ];
         keymap.extend(self.viewport.borrow().get_keymap());
         keymap.push([
@@ -161,7 +565,18 @@ impl AppMode for Teleoperate {
 
 impl UseViewport for Teleoperate {
     fn draw_in_viewport(&self, ctx: &mut Context) {
-        self.viewport.borrow().draw_in_viewport(ctx);
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        let color = if self.collision {
+            Color::Red
+        } else {
+            Color::DarkGray
+        };
+        for (i, pose) in self.preview.iter().enumerate() {
+            if (i + 1) % PREVIEW_DRAW_EVERY == 0 || i + 1 == self.preview.len() {
+                viewport.draw_robot_outline(ctx, pose, color);
+            }
+        }
     }
 
     fn x_bounds(&self) -> [f64; 2] {
@@ -173,6 +588,72 @@ impl UseViewport for Teleoperate {
     }
 
     fn info(&self) -> String {
-        format!("Velocity step: {:.2}", &self.increment)
+        let mut info = format!(
+            "Profile: {}, Velocity step: {:.2} m/s, {:.2} rad/s",
+            self.speed_profiles[self.current_profile].name,
+            self.linear_increment,
+            self.angular_increment
+        );
+        if self.collision {
+            info += if self.block_collisions {
+                ", COLLISION - blocked"
+            } else {
+                ", COLLISION"
+            };
+        }
+        let viewport_info = self.viewport.borrow().info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+        let width = 32;
+        let height = if self.holonomic { 5 } else { 4 };
+        if area.width < width || area.height < height {
+            return;
+        }
+        let gauge_area = Rect::new(
+            area.x + area.width - width,
+            area.y + area.height - height,
+            width,
+            height,
+        );
+        f.render_widget(Clear, gauge_area);
+        f.render_widget(
+            Block::default().title("Velocity").borders(Borders::ALL),
+            gauge_area,
+        );
+        let mut gauges = vec![("x", self.current_velocities.x, self.max_linear_velocity)];
+        if self.holonomic {
+            gauges.push(("y", self.current_velocities.y, self.max_linear_velocity));
+        }
+        gauges.push((
+            "θ",
+            self.current_velocities.theta,
+            self.max_angular_velocity,
+        ));
+        for (i, (name, value, max)) in gauges.iter().enumerate() {
+            let ratio = if *max > 0.0 {
+                (value.abs() / max).min(1.0)
+            } else {
+                0.0
+            };
+            let color = if *value < 0.0 {
+                Color::Magenta
+            } else {
+                Color::Green
+            };
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(color).bg(Color::Black))
+                .ratio(ratio)
+                .label(format!("{}: {:+.2}", name, value));
+            f.render_widget(
+                gauge,
+                Rect::new(gauge_area.x + 1, gauge_area.y + 1 + i as u16, width - 2, 1),
+            );
+        }
     }
 }
@@ -0,0 +1,119 @@
+//! A text form is a popup with one or more text fields, used when a mode needs the user to
+//! type values instead of using the mapped keys.
+
+use crossterm::event::KeyCode;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::Frame;
+
+/// Result of handling a key in the form.
+pub enum FormEvent {
+    None,
+    Confirm,
+    Cancel,
+}
+
+pub struct TextForm {
+    pub title: String,
+    pub fields: Vec<(String, String)>,
+    pub active: usize,
+}
+
+impl TextForm {
+    /// Creates a form with the given title and fields as (label, initial value).
+    pub fn new(title: &str, fields: Vec<(String, String)>) -> TextForm {
+        TextForm {
+            title: title.to_string(),
+            fields: fields,
+            active: 0,
+        }
+    }
+
+    /// Returns the current value of the field at the given index.
+    pub fn value(&self, index: usize) -> &str {
+        self.fields[index].1.trim()
+    }
+
+    pub fn handle_key(&mut self, key: &KeyCode) -> FormEvent {
+        match key {
+            KeyCode::Enter => return FormEvent::Confirm,
+            KeyCode::Esc => return FormEvent::Cancel,
+            KeyCode::Char(c) => self.fields[self.active].1.push(*c),
+            KeyCode::Backspace => {
+                self.fields[self.active].1.pop();
+            }
+            KeyCode::Tab | KeyCode::Down => self.active = (self.active + 1) % self.fields.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                self.active = (self.active + self.fields.len() - 1) % self.fields.len()
+            }
+            _ => (),
+        }
+        FormEvent::None
+    }
+
+    /// Draws the form as a popup in the center of the given area.
+    pub fn draw<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let height = (self.fields.len() as u16 + 3).min(area.height);
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length((area.height - height) / 2),
+                    Constraint::Length(height),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let popup = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(20),
+                    Constraint::Percentage(60),
+                    Constraint::Percentage(20),
+                ]
+                .as_ref(),
+            )
+            .split(vertical[1])[1];
+
+        let mut lines: Vec<Spans> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, (label, value))| {
+                if i == self.active {
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{}: ", label),
+                            Style::default()
+                                .fg(Color::Yellow)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(format!("{}_", value)),
+                    ])
+                } else {
+                    Spans::from(Span::raw(format!("{}: {}", label, value)))
+                }
+            })
+            .collect();
+        lines.push(Spans::from(Span::styled(
+            "Enter: confirm, Esc: cancel, Tab: next field",
+            Style::default().fg(Color::Gray),
+        )));
+
+        let form = Paragraph::new(lines).block(
+            Block::default()
+                .title(Span::styled(
+                    self.title.clone(),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL),
+        );
+        f.render_widget(Clear, popup);
+        f.render_widget(form, popup);
+    }
+}
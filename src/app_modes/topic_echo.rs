@@ -0,0 +1,419 @@
+//! Topic echo mode shows the messages of any topic, decoded at runtime, as a tree of fields.
+
+use crate::app_modes::{input, AppMode, BaseMode, Drawable};
+use crate::dynamic::{format_bandwidth, format_scalar, DynamicListener};
+use rosrust::MsgValue;
+use std::collections::HashSet;
+use tui::backend::Backend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use tui::Frame;
+
+/// Arrays longer than this are collapsed by default.
+const MAX_EXPANDED_ARRAY_LEN: usize = 16;
+
+/// A line of the message tree.
+struct TreeLine {
+    path: String,
+    depth: usize,
+    text: String,
+    expandable: bool,
+    expanded: bool,
+}
+
+/// Flattens a message into the lines to display, skipping the children of collapsed nodes.
+fn flatten(
+    value: &MsgValue,
+    name: &str,
+    path: &str,
+    depth: usize,
+    toggled: &HashSet<String>,
+    lines: &mut Vec<TreeLine>,
+) {
+    let (expanded_by_default, text) = match value {
+        MsgValue::Message(_) => (true, name.to_string()),
+        MsgValue::Array(items) => (
+            items.len() <= MAX_EXPANDED_ARRAY_LEN,
+            format!("{} [{}]", name, items.len()),
+        ),
+        _ => {
+            lines.push(TreeLine {
+                path: path.to_string(),
+                depth,
+                text: format!("{}: {}", name, format_scalar(value).unwrap_or_default()),
+                expandable: false,
+                expanded: false,
+            });
+            return;
+        }
+    };
+    let expanded = expanded_by_default != toggled.contains(path);
+    lines.push(TreeLine {
+        path: path.to_string(),
+        depth,
+        text,
+        expandable: true,
+        expanded,
+    });
+    if !expanded {
+        return;
+    }
+    // The children are only listed when shown, as arrays may have millions of items
+    let children: Vec<(String, &MsgValue)> = match value {
+        MsgValue::Message(fields) => {
+            let mut children: Vec<(String, &MsgValue)> =
+                fields.iter().map(|(k, v)| (k.clone(), v)).collect();
+            children.sort_by(|a, b| a.0.cmp(&b.0));
+            children
+        }
+        MsgValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (child_name, child) in children {
+        let child_path = format!("{}/{}", path, child_name);
+        flatten(child, &child_name, &child_path, depth + 1, toggled, lines);
+    }
+}
+
+pub struct TopicEcho {
+    topics: Vec<[String; 2]>,
+    topics_state: ListState,
+    listener: Option<DynamicListener>,
+    message: Option<Result<MsgValue, String>>,
+    message_count: u64,
+    paused: bool,
+    toggled: HashSet<String>,
+    lines: Vec<TreeLine>,
+    lines_state: ListState,
+}
+
+impl TopicEcho {
+    pub fn new() -> TopicEcho {
+        TopicEcho {
+            topics: Vec::new(),
+            topics_state: ListState::default(),
+            listener: None,
+            message: None,
+            message_count: 0,
+            paused: false,
+            toggled: HashSet::new(),
+            lines: Vec::new(),
+            lines_state: ListState::default(),
+        }
+    }
+
+    fn update_topics(&mut self) {
+        self.topics = match rosrust::topics() {
+            Ok(topics) => topics
+                .iter()
+                .map(|topic| [topic.name.to_string(), topic.datatype.to_string()])
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        self.topics.sort();
+        self.topics_state.select(if self.topics.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn subscribe(&mut self) {
+        let topic = match self.topics_state.selected() {
+            Some(i) => self.topics[i][0].clone(),
+            None => return,
+        };
+        self.message = None;
+        self.message_count = 0;
+        self.paused = false;
+        self.toggled.clear();
+        self.lines.clear();
+        self.lines_state.select(None);
+        self.listener = match DynamicListener::new(&topic, None) {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                self.message = Some(Err(e.to_string()));
+                None
+            }
+        };
+    }
+
+    fn unsubscribe(&mut self) {
+        self.listener = None;
+        self.message = None;
+        self.lines.clear();
+    }
+
+    fn update_lines(&mut self) {
+        self.lines.clear();
+        if let Some(Ok(MsgValue::Message(fields))) = &self.message {
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            for name in names {
+                flatten(&fields[name], name, name, 0, &self.toggled, &mut self.lines);
+            }
+        }
+        let selected = match self.lines_state.selected() {
+            _ if self.lines.is_empty() => None,
+            Some(i) => Some(i.min(self.lines.len() - 1)),
+            None => Some(0),
+        };
+        self.lines_state.select(selected);
+    }
+
+    fn toggle_selected(&mut self) {
+        let line = match self.lines_state.selected().and_then(|i| self.lines.get(i)) {
+            Some(line) if line.expandable => line,
+            _ => return,
+        };
+        let path = line.path.clone();
+        if !self.toggled.remove(&path) {
+            self.toggled.insert(path);
+        }
+        self.update_lines();
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        match self.lines_state.selected().and_then(|i| self.lines.get(i)) {
+            Some(line) if line.expandable && line.expanded != expanded => self.toggle_selected(),
+            _ => (),
+        }
+    }
+
+    fn select(state: &mut ListState, len: usize, offset: i64) {
+        if len == 0 {
+            return;
+        }
+        let i = match state.selected() {
+            Some(i) => (i as i64 + offset).rem_euclid(len as i64) as usize,
+            None => 0,
+        };
+        state.select(Some(i));
+    }
+
+    fn header(&self) -> Vec<Spans> {
+        let listener = match &self.listener {
+            Some(listener) => listener,
+            None => {
+                return match &self.message {
+                    Some(Err(e)) => vec![Spans::from(Span::styled(
+                        format!("Failed to subscribe: {}", e),
+                        Style::default().fg(Color::Red),
+                    ))],
+                    _ => vec![Spans::from(Span::raw(
+                        "Select a topic to echo its messages",
+                    ))],
+                };
+            }
+        };
+        let msg_type = listener.msg_type.read().unwrap().clone();
+        let stats = listener.stats.lock().unwrap();
+        let rate = stats
+            .rate()
+            .map_or("-".to_string(), |rate| format!("{:.2} Hz", rate));
+        let bandwidth = stats.bandwidth().map_or("-".to_string(), format_bandwidth);
+        let mut spans = vec![
+            Spans::from(vec![
+                Span::styled(
+                    listener.topic.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(" ({})", msg_type)),
+            ]),
+            Spans::from(Span::raw(format!(
+                "Messages: {}, rate: {}, bandwidth: {}",
+                listener.count(),
+                rate,
+                bandwidth
+            ))),
+        ];
+        if self.paused {
+            spans.push(Spans::from(Span::styled(
+                "Paused",
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        let error = match &self.message {
+            Some(Err(e)) => Some(e.clone()),
+            _ => listener.error.read().unwrap().clone(),
+        };
+        if let Some(e) = error {
+            spans.push(Spans::from(Span::styled(
+                e,
+                Style::default().fg(Color::Red),
+            )));
+        }
+        spans
+    }
+}
+
+impl<B: Backend> BaseMode<B> for TopicEcho {}
+
+impl AppMode for TopicEcho {
+    fn run(&mut self) {
+        if self.paused {
+            return;
+        }
+        let count = match &self.listener {
+            Some(listener) => listener.count(),
+            None => return,
+        };
+        if count == self.message_count {
+            return;
+        }
+        self.message_count = count;
+        self.message = self.listener.as_ref().unwrap().decode_last();
+        self.update_lines();
+    }
+
+    fn reset(&mut self) {
+        self.unsubscribe();
+        self.update_topics();
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "Topic echo shows the messages published on any topic.".to_string(),
+            "Select a topic from the list to subscribe to it, the fields can be expanded and collapsed.".to_string(),
+        ]
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        if self.listener.is_none() {
+            match input.as_str() {
+                input::UP => Self::select(&mut self.topics_state, self.topics.len(), -1),
+                input::DOWN => Self::select(&mut self.topics_state, self.topics.len(), 1),
+                input::CONFIRM => self.subscribe(),
+                input::NEXT => self.update_topics(),
+                _ => (),
+            }
+        } else {
+            match input.as_str() {
+                input::UP => Self::select(&mut self.lines_state, self.lines.len(), -1),
+                input::DOWN => Self::select(&mut self.lines_state, self.lines.len(), 1),
+                input::CONFIRM => self.toggle_selected(),
+                input::RIGHT => self.set_expanded(true),
+                input::LEFT => self.set_expanded(false),
+                input::PAUSE => self.paused = !self.paused,
+                input::CANCEL => self.unsubscribe(),
+                _ => (),
+            }
+        }
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        vec![
+            [
+                input::UP.to_string(),
+                "Selects the previous topic or field".to_string(),
+            ],
+            [
+                input::DOWN.to_string(),
+                "Selects the next topic or field".to_string(),
+            ],
+            [
+                input::CONFIRM.to_string(),
+                "Echoes the selected topic, or expands/collapses the selected field".to_string(),
+            ],
+            [
+                input::RIGHT.to_string(),
+                "Expands the selected field".to_string(),
+            ],
+            [
+                input::LEFT.to_string(),
+                "Collapses the selected field".to_string(),
+            ],
+            [
+                input::PAUSE.to_string(),
+                "Pauses/resumes the update of the message".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Goes back to the topic list".to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Refreshes the topic list".to_string(),
+            ],
+        ]
+    }
+
+    fn get_name(&self) -> String {
+        "Topic echo".to_string()
+    }
+}
+
+impl<B: Backend> Drawable<B> for TopicEcho {
+    fn draw(&self, f: &mut Frame<B>) {
+        let title_text = vec![Spans::from(Span::styled(
+            "Topic echo",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))];
+        let header = self.header();
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .horizontal_margin(20)
+            .constraints(
+                [
+                    Constraint::Length(3), // Title + 2 borders
+                    Constraint::Length(header.len() as u16 + 1),
+                    Constraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let title = Paragraph::new(title_text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        let header = Paragraph::new(header)
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        f.render_widget(title, areas[0]);
+        f.render_widget(header, areas[1]);
+
+        if self.listener.is_none() {
+            let items: Vec<ListItem> = self
+                .topics
+                .iter()
+                .map(|i| ListItem::new(format!("{} : {}", i[0], i[1])))
+                .collect();
+            let list = List::new(items)
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .block(Block::default().title("Topics").borders(Borders::ALL))
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, areas[2], &mut self.topics_state.clone());
+        } else {
+            let items: Vec<ListItem> = self
+                .lines
+                .iter()
+                .map(|line| {
+                    let marker = match (line.expandable, line.expanded) {
+                        (true, true) => "▾ ",
+                        (true, false) => "▸ ",
+                        _ => "  ",
+                    };
+                    ListItem::new(format!(
+                        "{}{}{}",
+                        "  ".repeat(line.depth),
+                        marker,
+                        line.text
+                    ))
+                })
+                .collect();
+            let list = List::new(items)
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .block(Block::default().title("Message").borders(Borders::ALL))
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, areas[2], &mut self.lines_state.clone());
+        }
+    }
+}
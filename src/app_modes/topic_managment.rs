@@ -1,7 +1,9 @@
 use crate::app_modes::{input, AppMode, BaseMode, Drawable};
 use crate::config::Color as ConfigColor;
 use crate::config::TermvizConfig;
-use crate::config::{ImageListenerConfig, ListenerConfig, ListenerConfigColor, PoseListenerConfig};
+use crate::config::{
+    Colormap, ImageListenerConfig, ListenerConfig, ListenerConfigColor, PoseListenerConfig,
+};
 use rand::Rng;
 use tui::backend::Backend;
 use tui::layout::{Alignment, Constraint, Direction, Layout};
@@ -275,6 +277,10 @@ impl TopicManager {
                 "sensor_msg/Image" => config.image_topics.push(ImageListenerConfig {
                     topic: topic[0].clone(),
                     rotation: 0,
+                    transport: "raw".to_string(),
+                    depth_range: None,
+                    colormap: Colormap::default(),
+                    camera_info_topic: "".to_string(),
                 }),
                 "geometry_msgs/PolygonStamped" => {
                     config.polygon_stamped_topics.push(ListenerConfigColor {
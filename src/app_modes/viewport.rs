@@ -2,18 +2,27 @@
 //! A mode can borrow the viewport to draw whatever is needed.
 
 use crate::app_modes::{input, AppMode, Drawable};
+use crate::config::{self, HudConfig, ImageListenerConfig, ViewBookmark};
 use crate::footprint::get_current_footprint;
+use crate::hud::HudListener;
+use crate::image::ImageListener;
 use crate::listeners::Listeners;
+use crate::map_file;
+use crate::recorder::Recorder;
+use crate::source;
+use crate::tf::TfListener;
 use crate::transformation::{self, iso2d_to_ros};
+use crossterm::event::KeyCode;
 use nalgebra::Isometry2;
 use std::sync::Arc;
 use tui::backend::Backend;
-use tui::layout::{Constraint, Layout};
+use tui::layout::{Constraint, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::canvas::{Canvas, Context, Line, Points};
-use tui::widgets::{Block, Borders};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState};
 use tui::Frame;
+use tui_image::{ColorMode, Image};
 
 /// Represents modes that use the viewport.
 pub trait UseViewport: AppMode {
@@ -33,6 +42,13 @@ pub trait UseViewport: AppMode {
 
     /// Returns additional information that will be displayed on the top bar of the viewport.
     fn info(&self) -> String;
+
+    /// Draws on top of the viewport, e.g., popups.
+    ///
+    /// # Arguments
+    /// - `_f`: the frame on which to draw.
+    /// - `_area`: the area covered by the viewport.
+    fn draw_overlay<B: Backend>(&self, _f: &mut Frame<B>, _area: Rect) {}
 }
 
 impl<B: Backend, T: UseViewport> Drawable<B> for T {
@@ -60,31 +76,53 @@ impl<B: Backend, T: UseViewport> Drawable<B> for T {
                 self.draw_in_viewport(ctx);
             });
         f.render_widget(canvas, chunks[0]);
+        self.draw_overlay(f, chunks[0]);
     }
 }
 
 pub struct Viewport {
     pub static_frame: String,
     pub robot_frame: String,
-    pub tf_listener: Arc<rustros_tf::TfListener>,
+    pub tf_listener: Arc<TfListener>,
     pub initial_bounds: Vec<f64>,
     pub footprint: Vec<(f64, f64)>,
     pub axis_length: f64,
     pub zoom: f64,
     pub zoom_factor: f64,
+    pub rotation: f64,
+    pub follow_robot: bool,
+    pub center: (f64, f64),
     pub terminal_size: (u16, u16),
     pub listeners: Listeners, // TODO split properly config and listeners
+    bookmarks: Vec<ViewBookmark>,
+    active_bookmark: Option<usize>,
+    view_picker: Option<ListState>, // Open list to jump to a bookmark
+    inset_images: Vec<ImageListener>,
+    active_inset: Option<usize>,
+    inset_size: u16, // Percentage of the width of the viewport
+    hud: Vec<HudListener>,
+    recorder: Option<Recorder>,
+    active_map: Option<usize>, // Map topic to save, the first one if not selected
+    message: String,           // Outcome of the last recording or map saving, until the next input
 }
 
+/// Default width of the image inset, in percentage of the viewport.
+const DEFAULT_INSET_SIZE: u16 = 30;
+/// Step for resizing the image inset, in percentage of the viewport.
+const INSET_SIZE_STEP: u16 = 10;
+
 impl Viewport {
     pub fn new(
         static_frame: &String,
         robot_frame: &String,
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         initial_bounds: &Vec<f64>,
         footprint: &Vec<(f64, f64)>,
         axis_length: f64,
         zoom_factor: f64,
+        bookmarks: &Vec<ViewBookmark>,
+        image_topics: &Vec<ImageListenerConfig>,
+        hud_topics: &Vec<HudConfig>,
         listeners: Listeners,
         terminal_size: (u16, u16),
     ) -> Viewport {
@@ -95,10 +133,26 @@ impl Viewport {
             initial_bounds: initial_bounds.clone(),
             zoom: 1.0,
             zoom_factor: zoom_factor,
+            rotation: 0.0,
+            follow_robot: true,
+            center: (0.0, 0.0),
             footprint: footprint.clone(),
             axis_length: axis_length,
             listeners: listeners,
             terminal_size: terminal_size,
+            bookmarks: bookmarks.clone(),
+            active_bookmark: None,
+            view_picker: None,
+            inset_images: image_topics
+                .iter()
+                .map(|config| ImageListener::new(config.clone()))
+                .collect(),
+            active_inset: None,
+            inset_size: DEFAULT_INSET_SIZE,
+            hud: hud_topics.iter().map(HudListener::new).collect(),
+            recorder: None,
+            active_map: None,
+            message: String::new(),
         }
     }
     pub fn get_frame_lines(
@@ -124,19 +178,362 @@ impl Viewport {
         });
         result
     }
+
+    /// Returns the point on which the view is centered, in the static frame.
+    ///
+    /// This is the robot position when following the robot, or the fixed center otherwise.
+    pub fn view_center(&self) -> (f64, f64) {
+        if !self.follow_robot {
+            return self.center;
+        }
+        match self.tf_listener.lookup_transform(
+            &self.static_frame,
+            &self.robot_frame,
+            rosrust::Time::new(),
+        ) {
+            Ok(tf) => (tf.transform.translation.x, tf.transform.translation.y),
+            Err(_e) => (0.0, 0.0),
+        }
+    }
+
+    /// Returns the horizontal and vertical bounds of a view centered on the given point.
+    ///
+    /// # Arguments
+    /// - `center`: the center of the view, in the static frame.
+    pub fn bounds_around(&self, center: (f64, f64)) -> ([f64; 2], [f64; 2]) {
+        let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
+        let center = self.to_view(center);
+        (
+            [
+                center.0 + self.initial_bounds[0] / self.zoom * scale_factor,
+                center.0 + self.initial_bounds[1] / self.zoom * scale_factor,
+            ],
+            [
+                center.1 + self.initial_bounds[2] / self.zoom,
+                center.1 + self.initial_bounds[3] / self.zoom,
+            ],
+        )
+    }
+
+    /// Transforms a point from the static frame into the rotated view.
+    pub fn to_view(&self, pt: (f64, f64)) -> (f64, f64) {
+        if self.rotation == 0.0 {
+            return pt;
+        }
+        let (sin, cos) = (-self.rotation).sin_cos();
+        (pt.0 * cos - pt.1 * sin, pt.0 * sin + pt.1 * cos)
+    }
+
+    /// Draws a line given in the static frame, taking into account the view rotation.
+    pub fn draw_line(&self, ctx: &mut Context, line: &Line) {
+        let p1 = self.to_view((line.x1, line.y1));
+        let p2 = self.to_view((line.x2, line.y2));
+        ctx.draw(&Line {
+            x1: p1.0,
+            y1: p1.1,
+            x2: p2.0,
+            y2: p2.1,
+            color: line.color,
+        });
+    }
+
+    /// Draws points given in the static frame, taking into account the view rotation.
+    pub fn draw_points(&self, ctx: &mut Context, coords: &[(f64, f64)], color: Color) {
+        if self.rotation == 0.0 {
+            ctx.draw(&Points { coords, color });
+        } else {
+            let rotated: Vec<(f64, f64)> = coords.iter().map(|pt| self.to_view(*pt)).collect();
+            ctx.draw(&Points {
+                coords: &rotated,
+                color,
+            });
+        }
+    }
+
+    /// Draws the footprint and the axes of the robot at the given pose.
+    ///
+    /// # Arguments
+    /// - `ctx`: the tui context where to draw.
+    /// - `pose`: the pose of the robot in the static frame.
+    /// - `color`: the color of the outline and of the axes.
+    pub fn draw_robot_outline(&self, ctx: &mut Context, pose: &Isometry2<f64>, color: Color) {
+        let pose_ros = iso2d_to_ros(pose);
+        for elem in &get_current_footprint(&pose_ros, &self.footprint) {
+            self.draw_line(
+                ctx,
+                &Line {
+                    x1: elem.0,
+                    y1: elem.1,
+                    x2: elem.2,
+                    y2: elem.3,
+                    color: color,
+                },
+            );
+        }
+        for mut line in Viewport::get_frame_lines(&pose_ros, self.axis_length) {
+            line.color = color;
+            self.draw_line(ctx, &line);
+        }
+    }
+
+    fn apply_bookmark(&mut self, index: usize) {
+        let bookmark = &self.bookmarks[index];
+        self.center = bookmark.center;
+        self.zoom = bookmark.zoom;
+        self.rotation = bookmark.rotation.to_radians();
+        self.follow_robot = bookmark.follow_robot;
+        self.active_bookmark = Some(index);
+    }
+
+    fn next_bookmark(&mut self) {
+        if self.bookmarks.is_empty() {
+            return;
+        }
+        let next = match self.active_bookmark {
+            Some(i) => (i + 1) % self.bookmarks.len(),
+            None => 0,
+        };
+        self.apply_bookmark(next);
+    }
+
+    fn open_view_picker(&mut self) {
+        if self.bookmarks.is_empty() {
+            return;
+        }
+        let mut state = ListState::default();
+        state.select(Some(self.active_bookmark.unwrap_or(0)));
+        self.view_picker = Some(state);
+    }
+
+    /// Shows the next image topic in the inset, or hides the inset after the last one.
+    /// Only the shown topic is subscribed.
+    fn next_inset(&mut self) {
+        if let Some(i) = self.active_inset {
+            self.inset_images[i].deactivate();
+        }
+        self.active_inset = match self.active_inset {
+            Some(i) if i + 1 < self.inset_images.len() => Some(i + 1),
+            Some(_) => None,
+            None if !self.inset_images.is_empty() => Some(0),
+            None => None,
+        };
+        if let Some(i) = self.active_inset {
+            self.inset_images[i].activate();
+        }
+    }
+
+    /// Starts recording the topics of the listeners to a new bag, or stops the current recording.
+    fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let path = recorder.path.clone();
+            self.message = match recorder.stop() {
+                Ok(()) => format!("Recorded {}", path),
+                Err(e) => format!("Recording failed: {}", e),
+            };
+            return;
+        }
+        if source::is_offline() {
+            self.message = "Recording requires a ROS connection".to_string();
+            return;
+        }
+        let path = format!(
+            "termviz_{}.bag",
+            chrono::Local::now().format("%Y-%m-%d-%H-%M-%S")
+        );
+        match Recorder::start(&path, &self.listeners.topics) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(e) => self.message = format!("Recording failed: {}", e),
+        }
+    }
+
+    fn next_map(&mut self) {
+        if self.listeners.maps.is_empty() {
+            return;
+        }
+        self.active_map = Some(match self.active_map {
+            Some(i) => (i + 1) % self.listeners.maps.len(),
+            None => 0,
+        });
+    }
+
+    /// Saves the last map received on the selected topic in the format of map_server.
+    fn save_map(&mut self) {
+        let map = match self.listeners.maps.get(self.active_map.unwrap_or(0)) {
+            Some(map) => map,
+            None => {
+                self.message = "No map topic".to_string();
+                return;
+            }
+        };
+        let grid = map.grid.read().unwrap();
+        let grid = match grid.as_ref() {
+            Some(grid) => grid,
+            None => {
+                self.message = format!("No map received on {}", map.config.topic);
+                return;
+            }
+        };
+        let name = format!(
+            "{}_{}",
+            map.config.topic.trim_start_matches('/').replace('/', "_"),
+            chrono::Local::now().format("%Y-%m-%d-%H-%M-%S")
+        );
+        self.message = match map_file::save(grid, &name) {
+            Ok(path) => format!("Saved {}", path),
+            Err(e) => format!("Could not save the map: {}", e),
+        };
+    }
+
+    fn save_bookmark(&mut self) {
+        let bookmark = ViewBookmark {
+            name: format!("View {}", self.bookmarks.len() + 1),
+            center: self.view_center(),
+            zoom: self.zoom,
+            rotation: self.rotation.to_degrees(),
+            follow_robot: self.follow_robot,
+        };
+        let to_store = bookmark.clone();
+        if config::update_user_config(|cfg| cfg.view_bookmarks.push(to_store)).is_ok() {
+            self.bookmarks.push(bookmark);
+            self.active_bookmark = Some(self.bookmarks.len() - 1);
+        }
+    }
+
+    fn draw_inset<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let image_sub = match self.active_inset {
+            Some(i) => &self.inset_images[i],
+            None => return,
+        };
+        let img = image_sub.img.read().unwrap();
+        let width = ((area.width as u32 * self.inset_size as u32 / 100) as u16)
+            .max(10)
+            .min(area.width);
+        // Terminal cells are about twice as high as wide
+        let height = if img.width() > 0 {
+            (width as u32 * img.height() / img.width() / 2) as u16 + 2
+        } else {
+            width / 4
+        }
+        .min(area.height.saturating_sub(1));
+        let inset_area = Rect::new(area.x + area.width - width, area.y + 1, width, height);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Span::raw("/".to_string() + &image_sub.config.topic));
+        let inner = block.inner(inset_area);
+        f.render_widget(Clear, inset_area);
+        f.render_widget(block, inset_area);
+        f.render_widget(
+            Image::with_img(img.clone()).color_mode(ColorMode::Rgb),
+            inner,
+        );
+    }
+
+    /// Draws the list of bookmarks in the center of the viewport, numbered like the keys to choose
+    /// them.
+    fn draw_view_picker<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let state = match &self.view_picker {
+            Some(state) => state,
+            None => return,
+        };
+        let items: Vec<ListItem> = self
+            .bookmarks
+            .iter()
+            .enumerate()
+            .map(|(i, bookmark)| {
+                let digit = match i {
+                    0..=8 => (i + 1).to_string(),
+                    9 => "0".to_string(),
+                    _ => " ".to_string(),
+                };
+                ListItem::new(format!("{}: {}", digit, bookmark.name))
+            })
+            .collect();
+        let width = 40.min(area.width);
+        let height = (self.bookmarks.len() as u16 + 2).min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "Views",
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .highlight_symbol(">> ");
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state.clone());
+    }
 }
 
 impl AppMode for Viewport {
     fn run(&mut self) {}
     fn reset(&mut self) {}
     fn handle_input(&mut self, input: &String) {
+        self.message.clear();
         match input.as_str() {
             input::ZOOM_IN => self.zoom += self.zoom_factor,
             input::ZOOM_OUT => self.zoom -= self.zoom_factor,
+            input::NEXT_VIEW => self.next_bookmark(),
+            input::PICK_VIEW => self.open_view_picker(),
+            input::SAVE_VIEW => self.save_bookmark(),
+            input::TOGGLE_INSET => self.next_inset(),
+            input::GROW_INSET => self.inset_size = (self.inset_size + INSET_SIZE_STEP).min(90),
+            input::SHRINK_INSET => {
+                self.inset_size = (self.inset_size - INSET_SIZE_STEP).max(INSET_SIZE_STEP)
+            }
+            input::RECORD => self.toggle_recording(),
+            input::NEXT_MAP => self.next_map(),
+            input::SAVE_MAP => self.save_map(),
             _ => return,
         }
     }
 
+    /// The view picker takes all the keys while open.
+    fn is_editing(&self) -> bool {
+        self.view_picker.is_some()
+    }
+
+    fn handle_text_input(&mut self, key: &KeyCode) {
+        let selected = match &self.view_picker {
+            Some(state) => state.selected().unwrap_or(0),
+            None => return,
+        };
+        let chosen = match key {
+            KeyCode::Up => {
+                let previous = (selected + self.bookmarks.len() - 1) % self.bookmarks.len();
+                self.view_picker.as_mut().unwrap().select(Some(previous));
+                return;
+            }
+            KeyCode::Down => {
+                let next = (selected + 1) % self.bookmarks.len();
+                self.view_picker.as_mut().unwrap().select(Some(next));
+                return;
+            }
+            KeyCode::Enter => Some(selected),
+            // 0 is the key after 9, like for the modes
+            KeyCode::Char(c) => match c.to_digit(10) {
+                Some(0) => Some(9),
+                Some(digit) => Some(digit as usize - 1),
+                None => return,
+            },
+            KeyCode::Esc => None,
+            _ => return,
+        };
+        match chosen {
+            Some(i) if i < self.bookmarks.len() => self.apply_bookmark(i),
+            Some(_) => return,
+            None => (),
+        }
+        self.view_picker = None;
+    }
+
     fn get_name(&self) -> String {
         "".to_string()
     }
@@ -155,94 +552,114 @@ impl AppMode for Viewport {
                 input::ZOOM_OUT.to_string(),
                 "Decreases the zoom.".to_string(),
             ],
+            [
+                input::NEXT_VIEW.to_string(),
+                "Jumps to the next view bookmark.".to_string(),
+            ],
+            [
+                input::PICK_VIEW.to_string(),
+                "Opens a list of the view bookmarks, to jump to one with the arrows and Enter or with its number."
+                    .to_string(),
+            ],
+            [
+                input::SAVE_VIEW.to_string(),
+                "Saves the current view as a new bookmark.".to_string(),
+            ],
+            [
+                input::TOGGLE_INSET.to_string(),
+                "Shows the next image topic in an inset, or hides it after the last one."
+                    .to_string(),
+            ],
+            [
+                input::GROW_INSET.to_string(),
+                "Enlarges the image inset.".to_string(),
+            ],
+            [
+                input::SHRINK_INSET.to_string(),
+                "Shrinks the image inset.".to_string(),
+            ],
+            [
+                input::RECORD.to_string(),
+                "Starts recording the shown topics and TF to a bag, or stops recording."
+                    .to_string(),
+            ],
+            [
+                input::NEXT_MAP.to_string(),
+                "Selects the next map topic to save.".to_string(),
+            ],
+            [
+                input::SAVE_MAP.to_string(),
+                "Saves the map of the selected topic as YAML and PGM files.".to_string(),
+            ],
         ]
     }
 }
 
 impl UseViewport for Viewport {
     fn x_bounds(&self) -> [f64; 2] {
-        let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
-        let res = self.tf_listener.clone().lookup_transform(
-            &self.static_frame,
-            &self.robot_frame,
-            rosrust::Time::new(),
-        );
-        match &res {
-            Ok(res) => res,
-            Err(_e) => {
-                return [
-                    self.initial_bounds[0] / self.zoom * scale_factor,
-                    self.initial_bounds[1] / self.zoom * scale_factor,
-                ]
-            }
-        };
-        let tf = res.as_ref().unwrap();
-
-        [
-            tf.transform.translation.x + self.initial_bounds[0] / self.zoom * scale_factor,
-            tf.transform.translation.x + self.initial_bounds[1] / self.zoom * scale_factor,
-        ]
+        self.bounds_around(self.view_center()).0
     }
     fn y_bounds(&self) -> [f64; 2] {
-        let scale_factor = self.terminal_size.0 as f64 / self.terminal_size.1 as f64 * 0.5;
-        let res = self.tf_listener.clone().lookup_transform(
-            &self.static_frame,
-            &self.robot_frame,
-            rosrust::Time::new(),
-        );
-        match &res {
-            Ok(res) => res,
-            Err(_e) => {
-                return [
-                    self.initial_bounds[2] / self.zoom * scale_factor,
-                    self.initial_bounds[3] / self.zoom * scale_factor,
-                ]
-            }
-        };
-        let tf = res.as_ref().unwrap();
-        [
-            tf.transform.translation.y + self.initial_bounds[2] / self.zoom,
-            tf.transform.translation.y + self.initial_bounds[3] / self.zoom,
-        ]
+        self.bounds_around(self.view_center()).1
     }
 
     fn info(&self) -> String {
-        "".to_string()
+        let mut items: Vec<String> = self.hud.iter().filter_map(|hud| hud.text()).collect();
+        if let Some(recorder) = &self.recorder {
+            match recorder.error() {
+                Some(e) => items.push(format!("REC error: {}", e)),
+                None => items.push(format!("REC {} msgs", recorder.message_count())),
+            }
+        }
+        if let Some(i) = self.active_map {
+            items.push(format!("Map: {}", self.listeners.maps[i].config.topic));
+        }
+        if !self.message.is_empty() {
+            items.push(self.message.clone());
+        }
+        if let Some(i) = self.active_bookmark {
+            items.push(format!("View: {}", self.bookmarks[i].name));
+        }
+        items.join(", ")
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.draw_inset(f, area);
+        self.draw_view_picker(f, area);
     }
     fn draw_in_viewport(&self, ctx: &mut Context) {
         for map in &self.listeners.maps {
-            ctx.draw(&Points {
-                coords: &map.points.read().unwrap(),
-                color: Color::Rgb(map.config.color.r, map.config.color.g, map.config.color.b),
-            });
+            self.draw_points(
+                ctx,
+                &map.points.read().unwrap(),
+                Color::Rgb(map.config.color.r, map.config.color.g, map.config.color.b),
+            );
         }
 
         ctx.layer();
         for pointcloud in &self.listeners.pointclouds {
             let points = &pointcloud.points.read().unwrap().clone();
             for pt in points {
-                ctx.draw(&Points {
-                    coords: &[(pt.point.x, pt.point.y)],
-                    color: pt.color,
-                })
+                self.draw_points(ctx, &[(pt.point.x, pt.point.y)], pt.color);
             }
         }
 
         ctx.layer();
         for line in self.listeners.markers.get_lines() {
-            ctx.draw(&line);
+            self.draw_line(ctx, &line);
         }
 
         ctx.layer();
         for laser in &self.listeners.lasers {
-            ctx.draw(&Points {
-                coords: &laser.points.read().unwrap(),
-                color: Color::Rgb(
+            self.draw_points(
+                ctx,
+                &laser.points.read().unwrap(),
+                Color::Rgb(
                     laser.config.color.r,
                     laser.config.color.g,
                     laser.config.color.b,
                 ),
-            });
+            );
         }
 
         ctx.layer();
@@ -257,43 +674,45 @@ impl UseViewport for Viewport {
         } else {
             iso2d_to_ros(&Isometry2::identity())
         };
-        get_current_footprint(&robot_pose, &self.footprint);
 
         for elem in get_current_footprint(&robot_pose, &self.footprint) {
-            ctx.draw(&Line {
-                x1: elem.0,
-                y1: elem.1,
-                x2: elem.2,
-                y2: elem.3,
-                color: Color::Blue,
-            });
+            self.draw_line(
+                ctx,
+                &Line {
+                    x1: elem.0,
+                    y1: elem.1,
+                    x2: elem.2,
+                    y2: elem.3,
+                    color: Color::Blue,
+                },
+            );
         }
 
         for line in Viewport::get_frame_lines(&robot_pose, self.axis_length) {
-            ctx.draw(&line);
+            self.draw_line(ctx, &line);
         }
 
         for pose_stamped in &self.listeners.pose_stamped {
             for line in pose_stamped.get_lines() {
-                ctx.draw(&line);
+                self.draw_line(ctx, &line);
             }
         }
 
         for polygon in &self.listeners.polygons {
             for line in polygon.get_lines() {
-                ctx.draw(&line);
+                self.draw_line(ctx, &line);
             }
         }
 
         for path in &self.listeners.paths {
             for line in path.get_lines() {
-                ctx.draw(&line)
+                self.draw_line(ctx, &line);
             }
         }
 
         for pose_array in &self.listeners.pose_array {
             for line in pose_array.get_lines() {
-                ctx.draw(&line);
+                self.draw_line(ctx, &line);
             }
         }
 
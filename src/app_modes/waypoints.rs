@@ -0,0 +1,453 @@
+//! Waypoints mode allows to edit a route of waypoints on the map and to execute it,
+//! sending the waypoints one by one as goals to a move_base action server.
+
+use crate::app_modes::ghost_pose::GhostPose;
+use crate::app_modes::viewport::{UseViewport, Viewport};
+use crate::app_modes::{input, AppMode, BaseMode};
+use crate::config::WaypointsConfig;
+use crate::move_base::{self, MoveBaseClient};
+use crate::transformation;
+use nalgebra::{Isometry2, Vector2};
+use rosrust_msg::actionlib_msgs::GoalStatus;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs::File;
+use std::path::PathBuf;
+use std::rc::Rc;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::Color;
+use tui::widgets::canvas::{Context, Line};
+use tui::Frame;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Waypoint {
+    pub x: f64,
+    pub y: f64,
+    pub yaw: f64,
+}
+
+/// A route as it is stored on disk.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Route {
+    pub frame_id: String,
+    pub waypoints: Vec<Waypoint>,
+}
+
+enum Execution {
+    Idle,
+    Running(usize),
+    Finished,
+    Failed(usize, String),
+}
+
+/// Represents the waypoints mode.
+pub struct Waypoints {
+    viewport: Rc<RefCell<Viewport>>,
+    ghost: GhostPose,
+    waypoints: Vec<Isometry2<f64>>,
+    selected: Option<usize>,
+    execution: Execution,
+    client: MoveBaseClient,
+    path_pub: rosrust::Publisher<rosrust_msg::nav_msgs::Path>,
+    pose_array_pub: rosrust::Publisher<rosrust_msg::geometry_msgs::PoseArray>,
+    file: PathBuf,
+    message: String,
+}
+
+impl Waypoints {
+    pub fn new(config: &WaypointsConfig, viewport: Rc<RefCell<Viewport>>) -> Waypoints {
+        let file = if config.file.is_empty() {
+            confy::get_configuration_file_path("termviz", "termviz")
+                .ok()
+                .and_then(|p| p.parent().map(|dir| dir.join("waypoints.yml")))
+                .unwrap_or(PathBuf::from("waypoints.yml"))
+        } else {
+            PathBuf::from(&config.file)
+        };
+        Waypoints {
+            ghost: GhostPose::new(viewport.clone()),
+            viewport: viewport,
+            waypoints: Vec::new(),
+            selected: None,
+            execution: Execution::Idle,
+            client: MoveBaseClient::new(&config.action),
+            path_pub: rosrust::publish(&config.path_topic, 1).unwrap(),
+            pose_array_pub: rosrust::publish(&config.pose_array_topic, 1).unwrap(),
+            file: file,
+            message: "".to_string(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        match self.execution {
+            Execution::Running(_) => true,
+            _ => false,
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = Some(index);
+        self.ghost.set_pose(self.waypoints[index]);
+    }
+
+    fn select_next(&mut self, forward: bool) {
+        if self.waypoints.is_empty() {
+            return;
+        }
+        let len = self.waypoints.len();
+        let index = match (self.selected, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+        self.select(index);
+    }
+
+    fn add_waypoint(&mut self) {
+        let index = match self.selected {
+            Some(i) => i + 1,
+            None => self.waypoints.len(),
+        };
+        self.waypoints.insert(index, self.ghost.pose);
+        self.selected = Some(index);
+    }
+
+    fn delete_waypoint(&mut self) {
+        if let Some(i) = self.selected {
+            self.waypoints.remove(i);
+            self.selected = if self.waypoints.is_empty() {
+                None
+            } else {
+                Some(i.min(self.waypoints.len() - 1))
+            };
+        }
+    }
+
+    fn move_waypoint(&mut self, forward: bool) {
+        if let Some(i) = self.selected {
+            if forward && i + 1 < self.waypoints.len() {
+                self.waypoints.swap(i, i + 1);
+                self.selected = Some(i + 1);
+            } else if !forward && i > 0 {
+                self.waypoints.swap(i, i - 1);
+                self.selected = Some(i - 1);
+            }
+        }
+    }
+
+    fn send_waypoint(&mut self, index: usize) {
+        let frame_id = self.viewport.borrow().static_frame.clone();
+        self.client.send_goal(&self.waypoints[index], &frame_id);
+        self.execution = Execution::Running(index);
+    }
+
+    fn execute(&mut self) {
+        if self.waypoints.is_empty() || self.is_running() {
+            return;
+        }
+        self.send_waypoint(0);
+    }
+
+    fn stop(&mut self) {
+        if let Execution::Running(i) = self.execution {
+            self.client.cancel();
+            self.execution = Execution::Failed(i, "Canceled".to_string());
+        }
+    }
+
+    fn publish(&mut self) {
+        let frame_id = self.viewport.borrow().static_frame.clone();
+        let stamp = rosrust::now();
+        let mut path = rosrust_msg::nav_msgs::Path::default();
+        path.header.frame_id = frame_id.clone();
+        path.header.stamp = stamp;
+        let mut pose_array = rosrust_msg::geometry_msgs::PoseArray::default();
+        pose_array.header = path.header.clone();
+        for waypoint in &self.waypoints {
+            let pose = transformation::iso2d_to_ros_pose(waypoint);
+            let mut pose_stamped = rosrust_msg::geometry_msgs::PoseStamped::default();
+            pose_stamped.header = path.header.clone();
+            pose_stamped.pose = pose.clone();
+            path.poses.push(pose_stamped);
+            pose_array.poses.push(pose);
+        }
+        self.path_pub.send(path).unwrap();
+        self.pose_array_pub.send(pose_array).unwrap();
+        self.message = format!("Published {} waypoints", self.waypoints.len());
+    }
+
+    fn save(&mut self) {
+        let route = Route {
+            frame_id: self.viewport.borrow().static_frame.clone(),
+            waypoints: self
+                .waypoints
+                .iter()
+                .map(|w| Waypoint {
+                    x: w.translation.x,
+                    y: w.translation.y,
+                    yaw: w.rotation.angle(),
+                })
+                .collect(),
+        };
+        let res = File::create(&self.file)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_yaml::to_writer(f, &route).map_err(|e| e.to_string()));
+        self.message = match res {
+            Ok(_) => format!("Saved to {}", self.file.display()),
+            Err(e) => format!("Error saving: {}", e),
+        };
+    }
+
+    fn load(&mut self) {
+        if self.is_running() {
+            return;
+        }
+        let res = File::open(&self.file)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_yaml::from_reader::<_, Route>(f).map_err(|e| e.to_string()));
+        let route = match res {
+            Ok(route) => route,
+            Err(e) => {
+                self.message = format!("Error loading: {}", e);
+                return;
+            }
+        };
+        let viewport = self.viewport.borrow();
+        let tf = match viewport.tf_listener.lookup_transform(
+            &viewport.static_frame,
+            &route.frame_id,
+            rosrust::Time::new(),
+        ) {
+            Ok(tf) => transformation::ros_to_iso2d(&tf.transform),
+            Err(_e) => {
+                self.message = format!(
+                    "Error loading: no transform from {} to {}",
+                    route.frame_id, viewport.static_frame
+                );
+                return;
+            }
+        };
+        self.waypoints = route
+            .waypoints
+            .iter()
+            .map(|w| tf * Isometry2::new(Vector2::new(w.x, w.y), w.yaw))
+            .collect();
+        self.selected = None;
+        self.execution = Execution::Idle;
+        self.message = format!(
+            "Loaded {} waypoints from {}",
+            self.waypoints.len(),
+            self.file.display()
+        );
+    }
+
+    fn progress(&self) -> String {
+        match &self.execution {
+            Execution::Idle => "Idle".to_string(),
+            Execution::Running(i) => format!("Running {}/{}", i + 1, self.waypoints.len()),
+            Execution::Finished => "Finished".to_string(),
+            Execution::Failed(i, reason) => format!("{} at {}", reason, i + 1),
+        }
+    }
+}
+
+impl<B: Backend> BaseMode<B> for Waypoints {}
+
+impl AppMode for Waypoints {
+    fn run(&mut self) {
+        self.ghost.update();
+        if let Execution::Running(i) = self.execution {
+            let state = self.client.get_state();
+            match state.status {
+                Some(GoalStatus::SUCCEEDED) => {
+                    if i + 1 < self.waypoints.len() {
+                        self.send_waypoint(i + 1);
+                    } else {
+                        self.execution = Execution::Finished;
+                    }
+                }
+                Some(status) if move_base::is_terminal(status) => {
+                    self.execution =
+                        Execution::Failed(i, move_base::status_name(status).to_string());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.ghost.reset();
+    }
+
+    fn handle_input(&mut self, input: &String) {
+        self.viewport.borrow_mut().handle_input(input);
+        if self.ghost.handle_input(input) {
+            return;
+        }
+        if self.is_running() {
+            if input == input::CANCEL_GOAL {
+                self.stop();
+            }
+            return;
+        }
+        match input.as_str() {
+            input::CONFIRM => self.add_waypoint(),
+            input::NEXT => self.select_next(true),
+            input::PREVIOUS => self.select_next(false),
+            input::DELETE => self.delete_waypoint(),
+            input::MOVE_FORWARD => self.move_waypoint(true),
+            input::MOVE_BACKWARD => self.move_waypoint(false),
+            input::EXECUTE => self.execute(),
+            input::PUBLISH => self.publish(),
+            input::SAVE => self.save(),
+            input::LOAD => self.load(),
+            input::CANCEL => {
+                self.selected = None;
+                self.reset();
+            }
+            _ => (),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "Waypoints".to_string()
+    }
+
+    fn get_description(&self) -> Vec<String> {
+        vec![
+            "This mode allows to edit a route of waypoints and to execute it.".to_string(),
+            "Waypoints are placed by moving the outline of the robot, like in send pose mode."
+                .to_string(),
+            "When executed, the waypoints are sent one by one as goals to the action server."
+                .to_string(),
+            "The route can be saved to and loaded from a YAML file.".to_string(),
+        ]
+    }
+
+    fn get_keymap(&self) -> Vec<[String; 2]> {
+        let mut keymap = self.ghost.get_keymap();
+        keymap.extend(vec![
+            [
+                input::CONFIRM.to_string(),
+                "Adds a waypoint after the selected one.".to_string(),
+            ],
+            [
+                input::CANCEL.to_string(),
+                "Clears the selection and resets the desired pose.".to_string(),
+            ],
+            [
+                input::NEXT.to_string(),
+                "Selects the next waypoint.".to_string(),
+            ],
+            [
+                input::PREVIOUS.to_string(),
+                "Selects the previous waypoint.".to_string(),
+            ],
+            [
+                input::DELETE.to_string(),
+                "Deletes the selected waypoint.".to_string(),
+            ],
+            [
+                input::MOVE_FORWARD.to_string(),
+                "Moves the selected waypoint later in the route.".to_string(),
+            ],
+            [
+                input::MOVE_BACKWARD.to_string(),
+                "Moves the selected waypoint earlier in the route.".to_string(),
+            ],
+            [
+                input::EXECUTE.to_string(),
+                "Sends the waypoints one by one as goals.".to_string(),
+            ],
+            [
+                input::CANCEL_GOAL.to_string(),
+                "Stops the execution of the route.".to_string(),
+            ],
+            [
+                input::PUBLISH.to_string(),
+                "Publishes the route as path and pose array.".to_string(),
+            ],
+            [input::SAVE.to_string(), "Saves the route.".to_string()],
+            [input::LOAD.to_string(), "Loads the route.".to_string()],
+        ]);
+        keymap.extend(self.viewport.borrow().get_keymap());
+        keymap
+    }
+}
+
+impl UseViewport for Waypoints {
+    fn draw_in_viewport(&self, ctx: &mut Context) {
+        let viewport = self.viewport.borrow();
+        viewport.draw_in_viewport(ctx);
+        let current = match self.execution {
+            Execution::Running(i) | Execution::Failed(i, _) => Some(i),
+            _ => None,
+        };
+        for pair in self.waypoints.windows(2) {
+            viewport.draw_line(
+                ctx,
+                &Line {
+                    x1: pair[0].translation.x,
+                    y1: pair[0].translation.y,
+                    x2: pair[1].translation.x,
+                    y2: pair[1].translation.y,
+                    color: Color::Cyan,
+                },
+            );
+        }
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            let color = match current {
+                Some(c) if c == i => Color::Yellow,
+                Some(c) if c > i => Color::Green,
+                _ => Color::Cyan,
+            };
+            if self.selected == Some(i) || current == Some(i) {
+                viewport.draw_robot_outline(ctx, waypoint, color);
+            } else {
+                for mut line in Viewport::get_frame_lines(
+                    &transformation::iso2d_to_ros(waypoint),
+                    viewport.axis_length,
+                ) {
+                    line.color = color;
+                    viewport.draw_line(ctx, &line);
+                }
+            }
+        }
+        self.ghost.draw(ctx);
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        self.ghost.bounds().0
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        self.ghost.bounds().1
+    }
+
+    fn info(&self) -> String {
+        let selected = match self.selected {
+            Some(i) => (i + 1).to_string(),
+            None => "-".to_string(),
+        };
+        let mut info = format!(
+            "Waypoints: {}, Selected: {}, Route: {}",
+            self.waypoints.len(),
+            selected,
+            self.progress()
+        );
+        if !self.message.is_empty() {
+            info += &format!(", {}", self.message);
+        }
+        let viewport_info = self.viewport.borrow().info();
+        if !viewport_info.is_empty() {
+            info += &format!(", {}", viewport_info);
+        }
+        info
+    }
+
+    fn draw_overlay<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        self.viewport.borrow().draw_overlay(f, area);
+    }
+}
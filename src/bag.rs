@@ -0,0 +1,629 @@
+//! Reading and writing of ROS1 bag files (format version 2.0).
+//!
+//! Only uncompressed chunks are supported, which is what `rosbag record` writes by default, and
+//! the bags are read through their index.
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8] = b"#ROSBAG V2.0\n";
+/// The bag header record is padded to this size, to be rewritten when closing the bag.
+const BAG_HEADER_LEN: usize = 4096;
+/// Size of the chunks written, messages are buffered until a chunk is full.
+const CHUNK_SIZE: usize = 768 * 1024;
+/// Maximum time for which messages are buffered, to lose little data if termviz is killed.
+const CHUNK_DURATION: Duration = Duration::from_secs(1);
+
+const OP_MSG_DATA: u8 = 0x02;
+const OP_BAG_HEADER: u8 = 0x03;
+const OP_INDEX_DATA: u8 = 0x04;
+const OP_CHUNK: u8 = 0x05;
+const OP_CHUNK_INFO: u8 = 0x06;
+const OP_CONNECTION: u8 = 0x07;
+
+/// Converts a ROS time to nanoseconds.
+pub fn to_nanos(time: &rosrust::Time) -> i64 {
+    time.sec as i64 * 1_000_000_000 + time.nsec as i64
+}
+
+fn time_bytes(nanos: i64) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    LittleEndian::write_u32(&mut bytes[0..4], (nanos / 1_000_000_000) as u32);
+    LittleEndian::write_u32(&mut bytes[4..8], (nanos % 1_000_000_000) as u32);
+    bytes
+}
+
+fn read_time(bytes: &[u8]) -> i64 {
+    LittleEndian::read_u32(&bytes[0..4]) as i64 * 1_000_000_000
+        + LittleEndian::read_u32(&bytes[4..8]) as i64
+}
+
+/// A topic recorded in the bag.
+#[derive(Clone)]
+pub struct Connection {
+    pub topic: String,
+    pub msg_type: String,
+    pub md5sum: String,
+    pub definition: String,
+}
+
+/// A message of the bag, whose data is read from the file when needed.
+pub struct BagMessage {
+    pub conn: u32,
+    pub time: i64, // In nanoseconds
+    pos: u64,      // Position of the message record in the file
+}
+
+/// A record of the bag, with its header fields and the position of its data.
+struct Record {
+    fields: HashMap<String, Vec<u8>>,
+    data_pos: u64,
+    data_len: usize,
+}
+
+impl Record {
+    /// Reads the header of the record at the given position, the data is read on demand.
+    fn read(mut file: &File, file_len: u64, pos: u64) -> Result<Record, String> {
+        file.seek(SeekFrom::Start(pos)).map_err(|e| e.to_string())?;
+        let header = read_length_prefixed(file, file_len)?;
+        let data_len = read_u32(file)? as usize;
+        let data_pos = pos + 4 + header.len() as u64 + 4;
+        if data_pos + data_len as u64 > file_len {
+            return Err("unexpected end of file".to_string());
+        }
+        let fields = parse_fields(&header)?
+            .into_iter()
+            .map(|(name, value)| (name, value.to_vec()))
+            .collect();
+        Ok(Record {
+            fields,
+            data_pos,
+            data_len,
+        })
+    }
+
+    /// Position of the next record.
+    fn end(&self) -> u64 {
+        self.data_pos + self.data_len as u64
+    }
+
+    fn data(&self, mut file: &File) -> Result<Vec<u8>, String> {
+        let mut data = vec![0; self.data_len];
+        file.seek(SeekFrom::Start(self.data_pos))
+            .and_then(|_| file.read_exact(&mut data))
+            .map_err(|e| e.to_string())?;
+        Ok(data)
+    }
+
+    fn op(&self) -> Result<u8, String> {
+        self.field(1, "op").map(|op| op[0])
+    }
+
+    fn field(&self, len: usize, name: &str) -> Result<&[u8], String> {
+        match self.fields.get(name) {
+            Some(value) if value.len() >= len => Ok(value.as_slice()),
+            _ => Err(format!("missing or invalid field {} in record", name)),
+        }
+    }
+
+    fn u32_field(&self, name: &str) -> Result<u32, String> {
+        self.field(4, name).map(LittleEndian::read_u32)
+    }
+
+    fn u64_field(&self, name: &str) -> Result<u64, String> {
+        self.field(8, name).map(LittleEndian::read_u64)
+    }
+
+    fn string_field(&self, name: &str) -> Result<String, String> {
+        self.field(0, name)
+            .map(|value| String::from_utf8_lossy(value).to_string())
+    }
+}
+
+fn read_u32(mut file: &File) -> Result<u32, String> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(LittleEndian::read_u32(&bytes))
+}
+
+/// Reads a length-prefixed block, checking its length against the size of the file.
+fn read_length_prefixed(mut file: &File, file_len: u64) -> Result<Vec<u8>, String> {
+    let len = read_u32(file)? as usize;
+    if len as u64 > file_len {
+        return Err("unexpected end of file".to_string());
+    }
+    let mut bytes = vec![0; len];
+    file.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+fn length_prefixed(bytes: &[u8], pos: usize) -> Result<&[u8], String> {
+    let len = bytes
+        .get(pos..pos + 4)
+        .map(LittleEndian::read_u32)
+        .ok_or("unexpected end of file")? as usize;
+    bytes
+        .get(pos + 4..pos + 4 + len)
+        .ok_or_else(|| "unexpected end of file".to_string())
+}
+
+/// Parses a list of length-prefixed "name=value" fields.
+fn parse_fields(mut bytes: &[u8]) -> Result<HashMap<String, &[u8]>, String> {
+    let mut fields = HashMap::new();
+    while !bytes.is_empty() {
+        let field = length_prefixed(bytes, 0)?;
+        let separator = field
+            .iter()
+            .position(|b| *b == b'=')
+            .ok_or("invalid header field")?;
+        fields.insert(
+            String::from_utf8_lossy(&field[..separator]).to_string(),
+            &field[separator + 1..],
+        );
+        bytes = &bytes[4 + field.len()..];
+    }
+    Ok(fields)
+}
+
+fn write_fields(out: &mut Vec<u8>, fields: &[(&str, &[u8])]) {
+    let len: usize = fields
+        .iter()
+        .map(|(name, value)| 4 + name.len() + 1 + value.len())
+        .sum();
+    out.extend_from_slice(&(len as u32).to_le_bytes());
+    for (name, value) in fields {
+        out.extend_from_slice(&((name.len() + 1 + value.len()) as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.push(b'=');
+        out.extend_from_slice(value);
+    }
+}
+
+fn write_record(out: &mut Vec<u8>, fields: &[(&str, &[u8])], data: &[u8]) {
+    write_fields(out, fields);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+/// A bag read through its index, with its messages sorted by time. The data of the messages
+/// stays in the file until played.
+pub struct Bag {
+    file: File,
+    file_len: u64,
+    pub connections: HashMap<u32, Connection>,
+    pub messages: Vec<BagMessage>,
+}
+
+impl Bag {
+    pub fn open(path: &str) -> Result<Bag, String> {
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let file_len = file.metadata().map_err(|e| e.to_string())?.len();
+        let mut magic = vec![0; MAGIC.len()];
+        if file.read_exact(&mut magic).is_err() || magic != MAGIC {
+            return Err("not a bag of version 2.0".to_string());
+        }
+        let header = Record::read(&file, file_len, MAGIC.len() as u64)?;
+        if header.op()? != OP_BAG_HEADER {
+            return Err("missing bag header".to_string());
+        }
+        // The index is written when the recording is stopped
+        let index_pos = header.u64_field("index_pos")?;
+        if index_pos == 0 {
+            return Err("the bag has no index, run `rosbag reindex` on it".to_string());
+        }
+        let mut bag = Bag {
+            file,
+            file_len,
+            connections: HashMap::new(),
+            messages: Vec::new(),
+        };
+        let mut chunk_positions = Vec::new();
+        let mut pos = index_pos;
+        while pos < file_len {
+            let record = Record::read(&bag.file, file_len, pos)?;
+            match record.op()? {
+                OP_CONNECTION => bag.add_connection(&record)?,
+                OP_CHUNK_INFO => chunk_positions.push(record.u64_field("chunk_pos")?),
+                _ => (),
+            }
+            pos = record.end();
+        }
+        for chunk_pos in chunk_positions {
+            bag.read_chunk_index(chunk_pos)?;
+        }
+        if let Some(msg) = bag
+            .messages
+            .iter()
+            .find(|msg| !bag.connections.contains_key(&msg.conn))
+        {
+            return Err(format!("message of unknown connection {}", msg.conn));
+        }
+        bag.messages.sort_by_key(|msg| msg.time);
+        Ok(bag)
+    }
+
+    fn add_connection(&mut self, record: &Record) -> Result<(), String> {
+        let conn = record.u32_field("conn")?;
+        let data = record.data(&self.file)?;
+        let header = parse_fields(&data)?;
+        let get = |name: &str| {
+            header
+                .get(name)
+                .map_or("".to_string(), |v| String::from_utf8_lossy(v).to_string())
+        };
+        self.connections.entry(conn).or_insert(Connection {
+            topic: record.string_field("topic")?,
+            msg_type: get("type"),
+            md5sum: get("md5sum"),
+            definition: get("message_definition"),
+        });
+        Ok(())
+    }
+
+    /// Adds the messages of a chunk, listed in the index records following it.
+    fn read_chunk_index(&mut self, chunk_pos: u64) -> Result<(), String> {
+        let chunk = Record::read(&self.file, self.file_len, chunk_pos)?;
+        if chunk.op()? != OP_CHUNK {
+            return Err(format!("no chunk at position {}", chunk_pos));
+        }
+        let compression = chunk.string_field("compression")?;
+        if compression != "none" {
+            return Err(format!("compression {} is not supported", compression));
+        }
+        let mut pos = chunk.end();
+        while pos < self.file_len {
+            let record = Record::read(&self.file, self.file_len, pos)?;
+            if record.op()? != OP_INDEX_DATA {
+                break;
+            }
+            let conn = record.u32_field("conn")?;
+            // Each entry is the time of a message and its offset in the chunk data
+            for entry in record.data(&self.file)?.chunks_exact(12) {
+                self.messages.push(BagMessage {
+                    conn,
+                    time: read_time(&entry[0..8]),
+                    pos: chunk.data_pos + LittleEndian::read_u32(&entry[8..12]) as u64,
+                });
+            }
+            pos = record.end();
+        }
+        Ok(())
+    }
+
+    /// Reads the serialized message from the file.
+    pub fn data(&self, msg: &BagMessage) -> Result<Vec<u8>, String> {
+        let record = Record::read(&self.file, self.file_len, msg.pos)?;
+        if record.op()? != OP_MSG_DATA {
+            return Err(format!("no message at position {}", msg.pos));
+        }
+        record.data(&self.file)
+    }
+
+    /// Returns the time of the first and of the last message, in nanoseconds.
+    pub fn time_range(&self) -> (i64, i64) {
+        match (self.messages.first(), self.messages.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => (0, 0),
+        }
+    }
+}
+
+/// Statistics of a connection in a chunk, to write the index.
+#[derive(Default)]
+struct ChunkConnection {
+    index: Vec<u8>,
+    count: u32,
+}
+
+struct ChunkInfo {
+    pos: u64,
+    start_time: i64,
+    end_time: i64,
+    counts: Vec<(u32, u32)>,
+}
+
+/// Writes a bag, the bag is only valid after closing it.
+pub struct BagWriter {
+    file: File,
+    connections: Vec<Connection>,
+    chunk: Vec<u8>,
+    chunk_connections: HashMap<u32, ChunkConnection>,
+    chunk_times: Option<(i64, i64)>,
+    chunk_started: Option<Instant>,
+    chunk_infos: Vec<ChunkInfo>,
+    pub message_count: usize,
+}
+
+impl BagWriter {
+    pub fn create(path: &str) -> io::Result<BagWriter> {
+        let mut file = File::create(path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&bag_header(0, 0, 0))?;
+        Ok(BagWriter {
+            file,
+            connections: Vec::new(),
+            chunk: Vec::new(),
+            chunk_connections: HashMap::new(),
+            chunk_times: None,
+            chunk_started: None,
+            chunk_infos: Vec::new(),
+            message_count: 0,
+        })
+    }
+
+    /// Adds a connection and returns its ID, to write its messages.
+    pub fn add_connection(&mut self, connection: Connection) -> u32 {
+        let conn = self.connections.len() as u32;
+        // The connection is written in the chunk before its messages, and again in the index
+        write_connection(&mut self.chunk, conn, &connection);
+        self.connections.push(connection);
+        conn
+    }
+
+    pub fn write_message(&mut self, conn: u32, time: i64, data: &[u8]) -> io::Result<()> {
+        let offset = self.chunk.len() as u32;
+        write_record(
+            &mut self.chunk,
+            &[
+                ("op", &[OP_MSG_DATA]),
+                ("conn", &conn.to_le_bytes()),
+                ("time", &time_bytes(time)),
+            ],
+            data,
+        );
+        let stats = self.chunk_connections.entry(conn).or_default();
+        stats.index.extend_from_slice(&time_bytes(time));
+        stats.index.extend_from_slice(&offset.to_le_bytes());
+        stats.count += 1;
+        self.chunk_times = match self.chunk_times {
+            Some((start, end)) => Some((start.min(time), end.max(time))),
+            None => Some((time, time)),
+        };
+        self.message_count += 1;
+        let chunk_started = *self.chunk_started.get_or_insert_with(Instant::now);
+        if self.chunk.len() >= CHUNK_SIZE || chunk_started.elapsed() >= CHUNK_DURATION {
+            self.flush_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered messages as a chunk, followed by its index.
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        let (start_time, end_time) = match self.chunk_times.take() {
+            Some(times) => times,
+            None => return Ok(()),
+        };
+        self.chunk_started = None;
+        let pos = self.file.stream_position()?;
+        let mut out = Vec::new();
+        write_record(
+            &mut out,
+            &[
+                ("op", &[OP_CHUNK]),
+                ("compression", b"none"),
+                ("size", &(self.chunk.len() as u32).to_le_bytes()),
+            ],
+            &self.chunk,
+        );
+        let mut conns: Vec<u32> = self.chunk_connections.keys().copied().collect();
+        conns.sort_unstable();
+        let mut counts = Vec::new();
+        for conn in conns {
+            let stats = &self.chunk_connections[&conn];
+            write_record(
+                &mut out,
+                &[
+                    ("op", &[OP_INDEX_DATA]),
+                    ("ver", &1u32.to_le_bytes()),
+                    ("conn", &conn.to_le_bytes()),
+                    ("count", &stats.count.to_le_bytes()),
+                ],
+                &stats.index,
+            );
+            counts.push((conn, stats.count));
+        }
+        self.file.write_all(&out)?;
+        self.chunk_infos.push(ChunkInfo {
+            pos,
+            start_time,
+            end_time,
+            counts,
+        });
+        self.chunk.clear();
+        self.chunk_connections.clear();
+        Ok(())
+    }
+
+    /// Writes the last chunk and the index of the bag.
+    pub fn close(mut self) -> io::Result<()> {
+        self.flush_chunk()?;
+        let index_pos = self.file.stream_position()?;
+        let mut out = Vec::new();
+        for (conn, connection) in self.connections.iter().enumerate() {
+            write_connection(&mut out, conn as u32, connection);
+        }
+        for info in self.chunk_infos.iter() {
+            let mut data = Vec::new();
+            for (conn, count) in info.counts.iter() {
+                data.extend_from_slice(&conn.to_le_bytes());
+                data.extend_from_slice(&count.to_le_bytes());
+            }
+            write_record(
+                &mut out,
+                &[
+                    ("op", &[OP_CHUNK_INFO]),
+                    ("ver", &1u32.to_le_bytes()),
+                    ("chunk_pos", &info.pos.to_le_bytes()),
+                    ("start_time", &time_bytes(info.start_time)),
+                    ("end_time", &time_bytes(info.end_time)),
+                    ("count", &(info.counts.len() as u32).to_le_bytes()),
+                ],
+                &data,
+            );
+        }
+        self.file.write_all(&out)?;
+        self.file.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+        self.file.write_all(&bag_header(
+            index_pos,
+            self.connections.len() as u32,
+            self.chunk_infos.len() as u32,
+        ))?;
+        self.file.flush()
+    }
+}
+
+fn write_connection(out: &mut Vec<u8>, conn: u32, connection: &Connection) {
+    let mut data = Vec::new();
+    write_fields(
+        &mut data,
+        &[
+            ("topic", connection.topic.as_bytes()),
+            ("type", connection.msg_type.as_bytes()),
+            ("md5sum", connection.md5sum.as_bytes()),
+            ("message_definition", connection.definition.as_bytes()),
+        ],
+    );
+    // The connection header is not prefixed by its length in the record data
+    write_record(
+        out,
+        &[
+            ("op", &[OP_CONNECTION]),
+            ("conn", &conn.to_le_bytes()),
+            ("topic", connection.topic.as_bytes()),
+        ],
+        &data[4..],
+    );
+}
+
+/// Returns the bag header record, padded to its fixed size.
+fn bag_header(index_pos: u64, conn_count: u32, chunk_count: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_fields(
+        &mut out,
+        &[
+            ("op", &[OP_BAG_HEADER]),
+            ("index_pos", &index_pos.to_le_bytes()),
+            ("conn_count", &conn_count.to_le_bytes()),
+            ("chunk_count", &chunk_count.to_le_bytes()),
+        ],
+    );
+    let padding = BAG_HEADER_LEN - out.len() - 4;
+    out.extend_from_slice(&(padding as u32).to_le_bytes());
+    out.resize(BAG_HEADER_LEN, b' ');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a path in the temporary directory, unique to the test and to the process.
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("termviz_{}_{}.bag", name, std::process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn connection(topic: &str) -> Connection {
+        Connection {
+            topic: topic.to_string(),
+            msg_type: "std_msgs/String".to_string(),
+            md5sum: "992ce8a1687cec8c8bd883ec73ca41d1".to_string(),
+            definition: "string data\n".to_string(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip");
+        let mut writer = BagWriter::create(&path).unwrap();
+        let big = writer.add_connection(connection("/big"));
+        let small = writer.add_connection(connection("/small"));
+        // The big messages fill more than one chunk, and the times are not in order
+        let written = [
+            (big, 5, vec![1; CHUNK_SIZE / 3]),
+            (small, 1, vec![2; 10]),
+            (big, 4, vec![3; CHUNK_SIZE / 3]),
+            (big, 2, vec![4; CHUNK_SIZE / 3]),
+            (small, 6, vec![5; 10]),
+            (big, 3, vec![6; CHUNK_SIZE / 3]),
+        ];
+        for (conn, time, data) in written.iter() {
+            writer.write_message(*conn, *time, data).unwrap();
+        }
+        assert!(!writer.chunk_infos.is_empty());
+        writer.close().unwrap();
+
+        let bag = Bag::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bag.connections.len(), 2);
+        assert_eq!(bag.connections[&big].topic, "/big");
+        assert_eq!(bag.connections[&small].topic, "/small");
+        assert_eq!(bag.connections[&small].md5sum, connection("").md5sum);
+        assert_eq!(
+            bag.connections[&small].definition,
+            connection("").definition
+        );
+        let times: Vec<i64> = bag.messages.iter().map(|msg| msg.time).collect();
+        assert_eq!(times, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(bag.time_range(), (1, 6));
+        for msg in bag.messages.iter() {
+            let (conn, _, data) = written.iter().find(|(_, t, _)| *t == msg.time).unwrap();
+            assert_eq!(msg.conn, *conn);
+            assert_eq!(bag.data(msg).unwrap(), *data);
+        }
+    }
+
+    #[test]
+    fn rejects_bag_without_index() {
+        let path = temp_path("without_index");
+        let mut writer = BagWriter::create(&path).unwrap();
+        let conn = writer.add_connection(connection("/topic"));
+        writer.write_message(conn, 1, &[0; 10]).unwrap();
+        writer.flush_chunk().unwrap();
+        // The recording is interrupted before the index is written
+        drop(writer);
+        let result = Bag::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(e) if e.contains("no index")));
+    }
+
+    #[test]
+    fn rejects_compressed_chunk() {
+        let path = temp_path("compressed");
+        let chunk_pos = (MAGIC.len() + BAG_HEADER_LEN) as u64;
+        let mut chunk = Vec::new();
+        write_record(
+            &mut chunk,
+            &[
+                ("op", &[OP_CHUNK]),
+                ("compression", b"lz4"),
+                ("size", &0u32.to_le_bytes()),
+            ],
+            &[],
+        );
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(bag_header(chunk_pos + chunk.len() as u64, 1, 1));
+        bytes.extend(chunk);
+        write_connection(&mut bytes, 0, &connection("/topic"));
+        write_record(
+            &mut bytes,
+            &[
+                ("op", &[OP_CHUNK_INFO]),
+                ("ver", &1u32.to_le_bytes()),
+                ("chunk_pos", &chunk_pos.to_le_bytes()),
+                ("start_time", &time_bytes(0)),
+                ("end_time", &time_bytes(0)),
+                ("count", &0u32.to_le_bytes()),
+            ],
+            &[],
+        );
+        std::fs::write(&path, bytes).unwrap();
+        let result = Bag::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(e) if e.contains("compression lz4")));
+    }
+}
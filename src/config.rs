@@ -1,10 +1,11 @@
 use crate::app_modes::input;
 use confy;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::io;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tui::style::Color as TuiColor;
 
 fn default_int() -> i64 {
@@ -19,6 +20,113 @@ fn default_pose_length() -> f64 {
     0.2
 }
 
+fn default_goal_actions() -> Vec<GoalActionConfig> {
+    vec![GoalActionConfig {
+        action: "move_base".to_string(),
+    }]
+}
+
+fn default_covariance() -> Vec<f64> {
+    vec![0.25, 0.25, 0.06853891945200942]
+}
+
+/// Reads a covariance, which must be either the full 6x6 matrix or the x, y, yaw variances.
+fn deserialize_covariance<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<f64>, D::Error> {
+    let covariance = Vec::<f64>::deserialize(deserializer)?;
+    match covariance.len() {
+        3 | 36 => Ok(covariance),
+        n => Err(de::Error::custom(format!(
+            "covariance must have 3 or 36 values, found {}",
+            n
+        ))),
+    }
+}
+
+fn default_max_linear_velocity() -> f64 {
+    0.5
+}
+
+fn default_max_angular_velocity() -> f64 {
+    1.0
+}
+
+fn default_max_linear_acceleration() -> f64 {
+    1.0
+}
+
+fn default_max_angular_acceleration() -> f64 {
+    2.0
+}
+
+fn default_deadman_timeout() -> f64 {
+    1.0
+}
+
+fn default_collision_preview_time() -> f64 {
+    2.0
+}
+
+fn default_angular_increment() -> f64 {
+    0.2
+}
+
+fn default_transport() -> String {
+    "raw".to_string()
+}
+
+fn default_time_window() -> f64 {
+    10.0
+}
+
+fn default_plots() -> Vec<PlotConfig> {
+    vec![PlotConfig {
+        name: "Odometry".to_string(),
+        fields: vec![
+            "/odom/twist/twist/linear/x".to_string(),
+            "/odom/twist/twist/angular/z".to_string(),
+        ],
+        time_window: default_time_window(),
+        y_range: None,
+    }]
+}
+
+fn default_hud_topics() -> Vec<HudConfig> {
+    vec![
+        HudConfig {
+            topic: "battery_state".to_string(),
+            msg_type: HudMsgType::BatteryState,
+            label: "".to_string(),
+        },
+        HudConfig {
+            topic: "odom".to_string(),
+            msg_type: HudMsgType::Odometry,
+            label: "".to_string(),
+        },
+        HudConfig {
+            topic: "amcl_pose".to_string(),
+            msg_type: HudMsgType::PoseWithCovarianceStamped,
+            label: "".to_string(),
+        },
+    ]
+}
+
+fn default_favourite_services() -> Vec<String> {
+    vec![
+        "/global_localization".to_string(),
+        "/move_base/clear_costmaps".to_string(),
+    ]
+}
+
+fn default_zoom() -> f64 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn color_white() -> Color {
     Color {
         r: 255,
@@ -71,12 +179,81 @@ pub struct ImageListenerConfig {
     pub topic: String,
     #[serde(default = "default_int")]
     pub rotation: i64,
+    #[serde(default = "default_transport")]
+    pub transport: String, // raw, compressed or compressedDepth
+    #[serde(default)]
+    pub depth_range: Option<(f64, f64)>, // In meters, if None the range of each image is used
+    #[serde(default)]
+    pub colormap: Colormap, // For depth images
+    #[serde(default)]
+    pub camera_info_topic: String, // If empty, camera_info next to the image topic
+}
+
+/// Colormap of depth images.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    Gray,
+    Turbo,
+}
+
+impl Default for Colormap {
+    fn default() -> Self {
+        Colormap::Gray
+    }
+}
+
+/// Timestamp of the sent poses.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stamp {
+    Zero,
+    Now,
+}
+
+impl Default for Stamp {
+    fn default() -> Self {
+        Stamp::Zero
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SendPoseConfig {
     pub topic: String,
     pub msg_type: String,
+    #[serde(
+        default = "default_covariance",
+        deserialize_with = "deserialize_covariance"
+    )]
+    pub covariance: Vec<f64>, // Either the full 6x6 matrix or the x, y, yaw variances
+    #[serde(default)]
+    pub frame: String, // If empty, the fixed frame is used
+    #[serde(default)]
+    pub z: f64,
+    #[serde(default)]
+    pub stamp: Stamp,
+}
+
+/// Type of the messages of a HUD topic.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum HudMsgType {
+    BatteryState,
+    Odometry,
+    PoseWithCovarianceStamped,
+    String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HudConfig {
+    pub topic: String,
+    pub msg_type: HudMsgType,
+    #[serde(default)]
+    pub label: String, // If empty, a label depending on the type is used
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoalActionConfig {
+    pub action: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -94,21 +271,207 @@ pub struct MapListenerConfig {
     pub threshold: i8,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ViewBookmark {
+    pub name: String,
+    #[serde(default)]
+    pub center: (f64, f64), // Only used if follow_robot is false
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+    #[serde(default)]
+    pub rotation: f64, // In degrees
+    #[serde(default = "default_true")]
+    pub follow_robot: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlotConfig {
+    pub name: String,
+    pub fields: Vec<String>, // Topic followed by the path of the field, e.g., /odom/twist/twist/linear/x
+    #[serde(default = "default_time_window")]
+    pub time_window: f64, // In seconds
+    #[serde(default)]
+    pub y_range: Option<(f64, f64)>, // If None, the y axis is scaled automatically
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RosoutConfig {
+    pub topic: String,
+    pub max_entries: usize, // Older messages are dropped
+}
+
+impl Default for RosoutConfig {
+    fn default() -> RosoutConfig {
+        RosoutConfig {
+            topic: "/rosout_agg".to_string(),
+            max_entries: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiagnosticsConfig {
+    pub topic: String,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> DiagnosticsConfig {
+        DiagnosticsConfig {
+            topic: "/diagnostics_agg".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WaypointsConfig {
+    pub file: String, // If empty, the file is stored next to the user config
+    pub action: String,
+    pub path_topic: String,
+    pub pose_array_topic: String,
+}
+
+impl Default for WaypointsConfig {
+    fn default() -> WaypointsConfig {
+        WaypointsConfig {
+            file: "".to_string(),
+            action: "move_base".to_string(),
+            path_topic: "waypoints_path".to_string(),
+            pose_array_topic: "waypoints".to_string(),
+        }
+    }
+}
+
+/// Type of the teleop commands.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TeleopMsgType {
+    Twist,
+    TwistStamped,
+    #[cfg(feature = "ackermann")]
+    AckermannDriveStamped,
+    Joy,
+}
+
+impl Default for TeleopMsgType {
+    fn default() -> Self {
+        TeleopMsgType::Twist
+    }
+}
+
+/// Keys changing the steering angle of Ackermann commands.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Steering {
+    Rotation,
+    Lateral,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AckermannConfig {
+    pub steering: Steering,
+    pub max_steering_angle: f64, // In radians, reached at the maximum velocity
+}
+
+impl Default for AckermannConfig {
+    fn default() -> AckermannConfig {
+        AckermannConfig {
+            steering: Steering::Rotation,
+            max_steering_angle: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JoyConfig {
+    pub axis_x: i64, // Index of the axis, -1 if unused
+    pub axis_y: i64,
+    pub axis_theta: i64,
+    pub num_axes: usize,
+    pub enable_button: i64, // Button kept pressed while moving, -1 if unused
+    pub num_buttons: usize,
+}
+
+impl Default for JoyConfig {
+    fn default() -> JoyConfig {
+        JoyConfig {
+            axis_x: 1,
+            axis_y: 0,
+            axis_theta: 3,
+            num_axes: 8,
+            enable_button: -1,
+            num_buttons: 11,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeedProfile {
+    pub name: String,
+    pub linear_increment: f64,
+    pub angular_increment: f64,
+    pub max_linear_velocity: f64, // Capped by the global maximum velocities
+    pub max_angular_velocity: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TeleopConfig {
     pub default_increment: f64,
+    #[serde(default = "default_angular_increment")]
+    pub default_angular_increment: f64,
     pub increment_step: f64,
+    #[serde(default = "default_angular_increment")]
+    pub angular_increment_step: f64,
+    #[serde(default = "default_true")]
+    pub holonomic: bool,
+    #[serde(default)]
+    pub speed_profiles: Vec<SpeedProfile>, // If empty, slow, normal and fast profiles are preset
     pub cmd_vel_topic: String,
+    #[serde(default)]
+    pub msg_type: TeleopMsgType,
+    #[serde(default)]
+    pub frame_id: String, // If empty, the robot frame is used
+    #[serde(default)]
+    pub ackermann: AckermannConfig,
+    #[serde(default)]
+    pub joy: JoyConfig,
     pub publish_cmd_vel_when_idle: bool,
+    #[serde(default = "default_max_linear_velocity")]
+    pub max_linear_velocity: f64,
+    #[serde(default = "default_max_angular_velocity")]
+    pub max_angular_velocity: f64,
+    #[serde(default = "default_max_linear_acceleration")]
+    pub max_linear_acceleration: f64,
+    #[serde(default = "default_max_angular_acceleration")]
+    pub max_angular_acceleration: f64,
+    #[serde(default = "default_deadman_timeout")]
+    pub deadman_timeout: f64, // In seconds, 0 to disable
+    #[serde(default = "default_collision_preview_time")]
+    pub collision_preview_time: f64, // In seconds, 0 to disable
+    #[serde(default = "bool::default")]
+    pub block_collisions: bool,
 }
 
 impl Default for TeleopConfig {
     fn default() -> TeleopConfig {
         TeleopConfig {
             default_increment: 0.1,
+            default_angular_increment: default_angular_increment(),
             increment_step: 0.1,
+            angular_increment_step: default_angular_increment(),
+            holonomic: true,
+            speed_profiles: Vec::new(),
             cmd_vel_topic: "cmd_vel".to_string(),
+            msg_type: TeleopMsgType::default(),
+            frame_id: "".to_string(),
+            ackermann: AckermannConfig::default(),
+            joy: JoyConfig::default(),
             publish_cmd_vel_when_idle: true,
+            max_linear_velocity: default_max_linear_velocity(),
+            max_angular_velocity: default_max_angular_velocity(),
+            max_linear_acceleration: default_max_linear_acceleration(),
+            max_angular_acceleration: default_max_angular_acceleration(),
+            deadman_timeout: default_deadman_timeout(),
+            collision_preview_time: default_collision_preview_time(),
+            block_collisions: false,
         }
     }
 }
@@ -128,12 +491,28 @@ pub struct TermvizConfig {
     pub pose_array_topics: Vec<PoseListenerConfig>,
     pub pose_stamped_topics: Vec<PoseListenerConfig>,
     pub send_pose_topics: Vec<SendPoseConfig>,
+    #[serde(default = "default_goal_actions")]
+    pub goal_actions: Vec<GoalActionConfig>,
     pub target_framerate: i64,
     pub axis_length: f64,
     pub visible_area: Vec<f64>, //Borders of map from center in Meter
     pub zoom_factor: f64,
+    #[serde(default)]
+    pub view_bookmarks: Vec<ViewBookmark>,
+    #[serde(default = "default_hud_topics")]
+    pub hud_topics: Vec<HudConfig>,
     pub key_mapping: HashMap<String, String>,
     pub teleop: TeleopConfig,
+    #[serde(default)]
+    pub waypoints: WaypointsConfig,
+    #[serde(default = "default_plots")]
+    pub plots: Vec<PlotConfig>,
+    #[serde(default = "default_favourite_services")]
+    pub favourite_services: Vec<String>,
+    #[serde(default)]
+    pub rosout: RosoutConfig,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
 }
 
 impl Default for TermvizConfig {
@@ -163,6 +542,10 @@ impl Default for TermvizConfig {
             image_topics: vec![ImageListenerConfig {
                 topic: "image_rect".to_string(),
                 rotation: 0,
+                transport: "raw".to_string(),
+                depth_range: None,
+                colormap: Colormap::default(),
+                camera_info_topic: "".to_string(),
             }],
             pose_stamped_topics: vec![PoseListenerConfig {
                 topic: "pose_stamped".to_string(),
@@ -193,11 +576,18 @@ impl Default for TermvizConfig {
             send_pose_topics: vec![SendPoseConfig {
                 topic: "initialpose".to_string(),
                 msg_type: "PoseWithCovarianceStamped".to_string(),
+                covariance: default_covariance(),
+                frame: "".to_string(),
+                z: 0.0,
+                stamp: Stamp::Zero,
             }],
+            goal_actions: default_goal_actions(),
             target_framerate: 30,
             axis_length: 0.5,
             visible_area: vec![-5., 5., -5., 5.],
             zoom_factor: 0.1,
+            view_bookmarks: vec![],
+            hud_topics: default_hud_topics(),
             key_mapping: HashMap::from([
                 (input::UP.to_string(), "w".to_string()),
                 (input::DOWN.to_string(), "s".to_string()),
@@ -216,8 +606,36 @@ impl Default for TermvizConfig {
                 (input::SHOW_HELP.to_string(), "h".to_string()),
                 (input::MODE_2.to_string(), "t".to_string()),
                 (input::MODE_3.to_string(), "i".to_string()),
+                (input::CANCEL_GOAL.to_string(), "c".to_string()),
+                (input::DELETE.to_string(), "x".to_string()),
+                (input::MOVE_FORWARD.to_string(), ".".to_string()),
+                (input::MOVE_BACKWARD.to_string(), ",".to_string()),
+                (input::EXECUTE.to_string(), "g".to_string()),
+                (input::PUBLISH.to_string(), "p".to_string()),
+                (input::SAVE.to_string(), "S".to_string()),
+                (input::LOAD.to_string(), "L".to_string()),
+                (input::EDIT.to_string(), ":".to_string()),
+                (input::NEXT_VIEW.to_string(), "v".to_string()),
+                (input::SAVE_VIEW.to_string(), "V".to_string()),
+                (input::PICK_VIEW.to_string(), "B".to_string()),
+                (input::TOGGLE_OVERLAY.to_string(), "o".to_string()),
+                (input::PAUSE.to_string(), " ".to_string()),
+                (input::TOGGLE_INSET.to_string(), "I".to_string()),
+                (input::GROW_INSET.to_string(), "]".to_string()),
+                (input::SHRINK_INSET.to_string(), "[".to_string()),
+                (input::TOGGLE_AUTOSCALE.to_string(), "A".to_string()),
+                (input::RECORD.to_string(), "R".to_string()),
+                (input::NEXT_MAP.to_string(), "m".to_string()),
+                (input::SAVE_MAP.to_string(), "M".to_string()),
+                (input::NEXT_MODE.to_string(), "Tab".to_string()),
+                (input::PREVIOUS_MODE.to_string(), "BackTab".to_string()),
             ]),
             teleop: TeleopConfig::default(),
+            waypoints: WaypointsConfig::default(),
+            plots: default_plots(),
+            favourite_services: default_favourite_services(),
+            rosout: RosoutConfig::default(),
+            diagnostics: DiagnosticsConfig::default(),
         }
     }
 }
@@ -234,6 +652,9 @@ pub fn ask_store() -> bool {
     }
 }
 
+/// File from which the config was loaded, where the changes made in termviz are stored.
+static CONFIG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
 pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::ConfyError> {
     let user_path = confy::get_configuration_file_path("termviz", "termviz")?;
 
@@ -253,6 +674,8 @@ pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::
     if load_config_path.exists() {
         println!("Loading config from: {:?}", load_config_path);
         cfg = confy::load_path(load_config_path)?;
+        add_missing_keys(&mut cfg.key_mapping);
+        *CONFIG_PATH.lock().unwrap() = Some(load_config_path.to_path_buf());
     } else {
         // no config found, generate default
         println!("No config found, using default");
@@ -260,10 +683,43 @@ pub fn get_config(config_path: Option<&String>) -> Result<TermvizConfig, confy::
         if store {
             let res = confy::store("termviz", "termviz", &cfg);
             match res {
-                Ok(_) => println!("Stored default config at {:?}", user_path),
+                Ok(_) => {
+                    println!("Stored default config at {:?}", user_path);
+                    *CONFIG_PATH.lock().unwrap() = Some(user_path.clone());
+                }
                 Err(e) => println!("Error storing default config: {:?}", e),
             }
         }
     };
     Ok(cfg)
 }
+
+/// Adds the default keys of the inputs missing from a stored key mapping, e.g., inputs added
+/// after the config was written, unless the key is already used for another input.
+fn add_missing_keys(key_mapping: &mut HashMap<String, String>) {
+    for (input, key) in TermvizConfig::default().key_mapping {
+        if !key_mapping.contains_key(&input) && !key_mapping.values().any(|k| *k == key) {
+            key_mapping.insert(input, key);
+        }
+    }
+}
+
+/// Applies a modification to the config file that was loaded and stores it.
+///
+/// If termviz was started without a config file, the default config is modified and stored as
+/// the user config.
+pub fn update_user_config<F: FnOnce(&mut TermvizConfig)>(f: F) -> Result<(), confy::ConfyError> {
+    let path = match CONFIG_PATH.lock().unwrap().clone() {
+        Some(path) => path,
+        None => confy::get_configuration_file_path("termviz", "termviz")?,
+    };
+    let mut cfg = if path.exists() {
+        confy::load_path(&path)?
+    } else {
+        TermvizConfig::default()
+    };
+    f(&mut cfg);
+    confy::store_path(&path, &cfg)?;
+    *CONFIG_PATH.lock().unwrap() = Some(path);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Listener of the diagnostics, usually aggregated by the diagnostic aggregator.
+use crate::config::DiagnosticsConfig;
+use crate::source::{self, Subscription};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use rosrust_msg::diagnostic_msgs::{DiagnosticArray, DiagnosticStatus};
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Ok,
+    Warn,
+    Error,
+    Stale,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Ok, Level::Warn, Level::Error, Level::Stale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Ok => "OK",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Stale => "STALE",
+        }
+    }
+}
+
+fn level(status: &DiagnosticStatus) -> Level {
+    match status.level {
+        DiagnosticStatus::OK => Level::Ok,
+        DiagnosticStatus::WARN => Level::Warn,
+        DiagnosticStatus::ERROR => Level::Error,
+        _ => Level::Stale,
+    }
+}
+
+#[derive(Clone)]
+pub struct StatusEntry {
+    pub level: Level,
+    pub message: String,
+    pub hardware_id: String,
+    pub values: Vec<(String, String)>,
+}
+
+/// Returns the part of a status name before its last '/', empty for the top level.
+pub fn parent(name: &str) -> &str {
+    name.rfind('/').map_or("", |i| &name[..i])
+}
+
+pub struct DiagnosticsListener {
+    pub topic: String,
+    pub statuses: Arc<RwLock<BTreeMap<String, StatusEntry>>>,
+    _subscriber: Option<Subscription>,
+}
+
+impl DiagnosticsListener {
+    pub fn new(config: &DiagnosticsConfig) -> DiagnosticsListener {
+        let statuses = Arc::new(RwLock::new(BTreeMap::new()));
+        let cb_statuses = statuses.clone();
+        // Statuses are merged by name, since the raw diagnostics are published by many nodes
+        let subscriber = source::subscribe(&config.topic, 10, move |msg: DiagnosticArray| {
+            let mut statuses = cb_statuses.write().unwrap();
+            for status in msg.status {
+                let entry = StatusEntry {
+                    level: level(&status),
+                    message: status.message,
+                    hardware_id: status.hardware_id,
+                    values: status
+                        .values
+                        .into_iter()
+                        .map(|kv| (kv.key, kv.value))
+                        .collect(),
+                };
+                statuses.insert(status.name, entry);
+            }
+        })
+        .ok();
+
+        DiagnosticsListener {
+            topic: config.topic.clone(),
+            statuses,
+            _subscriber: subscriber,
+        }
+    }
+
+    /// Number of statuses per level, in the order of Level::ALL. Only the leaves of the tree
+    /// are counted, the groups of the aggregator summarize them.
+    pub fn summary(&self) -> [usize; 4] {
+        let statuses = self.statuses.read().unwrap();
+        let mut summary = [0; 4];
+        for (name, status) in statuses.iter() {
+            let prefix = format!("{}/", name);
+            let is_group = statuses
+                .range(prefix.clone()..)
+                .next()
+                .map_or(false, |(other, _)| other.starts_with(&prefix));
+            if !is_group {
+                summary[status.level as usize] += 1;
+            }
+        }
+        summary
+    }
+}
@@ -0,0 +1,237 @@
+//! Subscription to topics of any type.
+//!
+//! The messages are received as raw bytes and decoded at runtime from the message definition
+//! sent by the publisher in the connection headers, so no message type needs to be known at
+//! compile time.
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use rosrust;
+use rosrust::{DynamicMsg, MsgValue, RawMessage};
+
+/// Time window over which the rate and bandwidth of a topic are computed.
+const STATS_WINDOW: Duration = Duration::from_secs(5);
+
+/// Callback called with every decoded message.
+pub type MessageCallback = Box<dyn Fn(&MsgValue) + Send + Sync>;
+
+/// Keeps track of the arrival time and size of the last messages of a topic.
+#[derive(Default)]
+pub struct TopicStats {
+    received: VecDeque<(Instant, usize)>,
+}
+
+impl TopicStats {
+    fn add(&mut self, size: usize) {
+        let now = Instant::now();
+        self.received.push_back((now, size));
+        while let Some((stamp, _)) = self.received.front() {
+            if now.duration_since(*stamp) > STATS_WINDOW {
+                self.received.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn span(&self) -> Option<f64> {
+        match (self.received.front(), self.received.back()) {
+            (Some(first), Some(last)) if self.received.len() > 1 => {
+                Some(last.0.duration_since(first.0).as_secs_f64())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the average rate in Hz, if at least two messages were received.
+    pub fn rate(&self) -> Option<f64> {
+        self.span()
+            .filter(|span| *span > 0.)
+            .map(|span| (self.received.len() - 1) as f64 / span)
+    }
+
+    /// Returns the average bandwidth in bytes per second.
+    pub fn bandwidth(&self) -> Option<f64> {
+        self.span().filter(|span| *span > 0.).map(|span| {
+            self.received
+                .iter()
+                .skip(1)
+                .map(|(_, size)| size)
+                .sum::<usize>() as f64
+                / span
+        })
+    }
+}
+
+/// The last message received on a topic, not decoded yet.
+#[derive(Clone)]
+pub struct RawData {
+    pub count: u64,
+    pub bytes: Vec<u8>,
+}
+
+pub struct DynamicListener {
+    pub topic: String,
+    pub msg_type: Arc<RwLock<String>>,
+    pub error: Arc<RwLock<Option<String>>>,
+    pub stats: Arc<Mutex<TopicStats>>,
+    last: Arc<RwLock<Option<RawData>>>,
+    decoder: Arc<RwLock<Option<DynamicMsg>>>,
+    _subscriber: rosrust::Subscriber,
+}
+
+impl DynamicListener {
+    /// Subscribes to a topic of any type.
+    ///
+    /// # Arguments
+    /// - `topic`: the topic to subscribe to
+    /// - `callback`: if given, every message is decoded in the subscriber thread and passed to it
+    pub fn new(topic: &str, callback: Option<MessageCallback>) -> rosrust::error::Result<Self> {
+        let msg_type = Arc::new(RwLock::new(String::new()));
+        let error = Arc::new(RwLock::new(None));
+        let stats = Arc::new(Mutex::new(TopicStats::default()));
+        let last = Arc::new(RwLock::new(None));
+        let decoder = Arc::new(RwLock::new(None));
+
+        let cb_error = error.clone();
+        let cb_stats = stats.clone();
+        let cb_last = last.clone();
+        let cb_decoder = decoder.clone();
+        let header_msg_type = msg_type.clone();
+        let header_error = error.clone();
+        let header_decoder = decoder.clone();
+        let subscriber = rosrust::subscribe_with_ids_and_headers(
+            topic,
+            1,
+            move |msg: RawMessage, _caller_id: &str| {
+                cb_stats.lock().unwrap().add(msg.0.len());
+                if let Some(callback) = &callback {
+                    if let Some(decoder) = cb_decoder.read().unwrap().as_ref() {
+                        match decoder.decode(&msg.0[..]) {
+                            Ok(value) => callback(&MsgValue::Message(value)),
+                            Err(e) => *cb_error.write().unwrap() = Some(e.to_string()),
+                        }
+                    }
+                }
+                let mut last = cb_last.write().unwrap();
+                let count = last.as_ref().map_or(0, |data: &RawData| data.count) + 1;
+                *last = Some(RawData {
+                    count,
+                    bytes: msg.0,
+                });
+            },
+            move |headers: HashMap<String, String>| {
+                let msg_type = headers.get("type").cloned().unwrap_or_default();
+                let definition = headers
+                    .get("message_definition")
+                    .cloned()
+                    .unwrap_or_default();
+                match DynamicMsg::new(&msg_type, &definition) {
+                    Ok(decoder) => *header_decoder.write().unwrap() = Some(decoder),
+                    Err(e) => *header_error.write().unwrap() = Some(e.to_string()),
+                }
+                *header_msg_type.write().unwrap() = msg_type;
+            },
+        )?;
+
+        Ok(DynamicListener {
+            topic: topic.to_string(),
+            msg_type,
+            error,
+            stats,
+            last,
+            decoder,
+            _subscriber: subscriber,
+        })
+    }
+
+    /// Returns the number of messages received so far.
+    pub fn count(&self) -> u64 {
+        self.last
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |data| data.count)
+    }
+
+    /// Decodes the last received message, if any.
+    pub fn decode_last(&self) -> Option<Result<MsgValue, String>> {
+        let last = self.last.read().unwrap().clone()?;
+        let decoder = self.decoder.read().unwrap();
+        let decoder = match decoder.as_ref() {
+            Some(decoder) => decoder,
+            None => return Some(Err("No message definition received".to_string())),
+        };
+        Some(
+            decoder
+                .decode(&last.bytes[..])
+                .map(MsgValue::Message)
+                .map_err(|e| e.to_string()),
+        )
+    }
+}
+
+/// Returns the value found at `path` in a message, e.g., `pose/pose/position/x` or
+/// `ranges/0`.
+pub fn get_field<'a>(value: &'a MsgValue, path: &str) -> Option<&'a MsgValue> {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .try_fold(value, |value, name| match value {
+            MsgValue::Message(fields) => fields.get(name),
+            MsgValue::Array(items) => name.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+/// Converts a numeric value to f64. Times and durations are converted to seconds.
+pub fn to_f64(value: &MsgValue) -> Option<f64> {
+    match value {
+        MsgValue::Bool(v) => Some(if *v { 1. } else { 0. }),
+        MsgValue::I8(v) => Some(*v as f64),
+        MsgValue::I16(v) => Some(*v as f64),
+        MsgValue::I32(v) => Some(*v as f64),
+        MsgValue::I64(v) => Some(*v as f64),
+        MsgValue::U8(v) => Some(*v as f64),
+        MsgValue::U16(v) => Some(*v as f64),
+        MsgValue::U32(v) => Some(*v as f64),
+        MsgValue::U64(v) => Some(*v as f64),
+        MsgValue::F32(v) => Some(*v as f64),
+        MsgValue::F64(v) => Some(*v),
+        MsgValue::Time(t) => Some(t.sec as f64 + t.nsec as f64 * 1e-9),
+        MsgValue::Duration(d) => Some(d.sec as f64 + d.nsec as f64 * 1e-9),
+        _ => None,
+    }
+}
+
+/// Formats a value that is neither a message nor an array.
+pub fn format_scalar(value: &MsgValue) -> Option<String> {
+    match value {
+        MsgValue::String(v) => Some(format!("\"{}\"", v)),
+        MsgValue::Bool(v) => Some(v.to_string()),
+        MsgValue::Time(t) => Some(format!("{}.{:09}", t.sec, t.nsec)),
+        MsgValue::Duration(d) => Some(format!("{}.{:09}", d.sec, d.nsec.abs())),
+        MsgValue::I8(v) => Some(v.to_string()),
+        MsgValue::I16(v) => Some(v.to_string()),
+        MsgValue::I32(v) => Some(v.to_string()),
+        MsgValue::I64(v) => Some(v.to_string()),
+        MsgValue::U8(v) => Some(v.to_string()),
+        MsgValue::U16(v) => Some(v.to_string()),
+        MsgValue::U32(v) => Some(v.to_string()),
+        MsgValue::U64(v) => Some(v.to_string()),
+        MsgValue::F32(v) => Some(v.to_string()),
+        MsgValue::F64(v) => Some(v.to_string()),
+        MsgValue::Message(_) | MsgValue::Array(_) => None,
+    }
+}
+
+/// Formats a number of bytes per second with a binary prefix.
+pub fn format_bandwidth(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1024. * 1024. {
+        format!("{:.2} MB/s", bytes_per_sec / (1024. * 1024.))
+    } else if bytes_per_sec >= 1024. {
+        format!("{:.2} KB/s", bytes_per_sec / 1024.)
+    } else {
+        format!("{:.0} B/s", bytes_per_sec)
+    }
+}
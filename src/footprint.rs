@@ -1,3 +1,4 @@
+use crate::source;
 use crate::transformation;
 
 use rosrust;
@@ -15,6 +16,10 @@ pub fn get_default_footprint() -> Vec<(f64, f64)> {
 }
 
 pub fn get_footprint() -> Vec<(f64, f64)> {
+    if source::is_offline() {
+        // There is no parameter server to read it from
+        return get_default_footprint();
+    }
     let param = rosrust::param("/footprint");
     let mut result = Vec::<(f64, f64)>::new();
     match param {
@@ -62,3 +67,26 @@ pub fn get_current_footprint(
     result.push((p_end.0, p_end.1, p_start.0, p_start.1));
     result
 }
+
+/// Returns true if the point lies inside the polygon, using ray casting.
+pub fn point_in_polygon(pt: (f64, f64), polygon: &Vec<(f64, f64)>) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (xi, yi) = polygon[i];
+        let (xj, yj) = polygon[j];
+        if (yi > pt.1) != (yj > pt.1) && pt.0 < (xj - xi) * (pt.1 - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Returns the distance of the farthest footprint point from the robot origin.
+pub fn get_footprint_radius(footprint_poly: &Vec<(f64, f64)>) -> f64 {
+    footprint_poly
+        .iter()
+        .map(|pt| (pt.0 * pt.0 + pt.1 * pt.1).sqrt())
+        .fold(0.0, f64::max)
+}
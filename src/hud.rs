@@ -0,0 +1,109 @@
+//! Listeners of the robot state shown on the HUD line of the viewport.
+use crate::config::{HudConfig, HudMsgType};
+use crate::source::{self, Subscription};
+use std::sync::{Arc, RwLock};
+
+use rosrust;
+use rosrust_msg::geometry_msgs::PoseWithCovarianceStamped;
+use rosrust_msg::nav_msgs::Odometry;
+use rosrust_msg::sensor_msgs::BatteryState;
+use rosrust_msg::std_msgs;
+
+fn format_battery(msg: BatteryState) -> String {
+    if msg.percentage.is_nan() {
+        return "unknown".to_string();
+    }
+    format!("{:.0}%", msg.percentage * 100.0)
+}
+
+fn format_velocity(msg: Odometry) -> String {
+    format!(
+        "{:.2} m/s, {:.2} rad/s",
+        msg.twist.twist.linear.x, msg.twist.twist.angular.z
+    )
+}
+
+/// Formats the standard deviations of the position and of the yaw.
+fn format_covariance(msg: PoseWithCovarianceStamped) -> String {
+    let covariance = &msg.pose.covariance;
+    format!(
+        "σ {:.2} m, {:.1}°",
+        covariance[0].max(covariance[7]).sqrt(),
+        covariance[35].sqrt().to_degrees()
+    )
+}
+
+fn format_string(msg: std_msgs::String) -> String {
+    msg.data
+}
+
+pub struct HudListener {
+    pub label: String,
+    value: Arc<RwLock<Option<String>>>,
+    _subscriber: Option<Subscription>,
+}
+
+impl HudListener {
+    pub fn new(config: &HudConfig) -> HudListener {
+        let value = Arc::new(RwLock::new(None));
+        let (default_label, subscriber) = match config.msg_type {
+            HudMsgType::BatteryState => (
+                "Battery",
+                subscribe(&config.topic, value.clone(), format_battery),
+            ),
+            HudMsgType::Odometry => (
+                "Velocity",
+                subscribe(&config.topic, value.clone(), format_velocity),
+            ),
+            HudMsgType::PoseWithCovarianceStamped => (
+                "Localization",
+                subscribe(&config.topic, value.clone(), format_covariance),
+            ),
+            HudMsgType::String => (
+                "Mode",
+                subscribe(&config.topic, value.clone(), format_string),
+            ),
+        };
+        let label = if config.label.is_empty() {
+            default_label.to_string()
+        } else {
+            config.label.clone()
+        };
+        HudListener {
+            label,
+            value,
+            _subscriber: subscriber,
+        }
+    }
+
+    /// Returns the text to show, or None if no message was received yet.
+    /// A failed subscription is shown in place of the value.
+    pub fn text(&self) -> Option<String> {
+        self.value
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|value| format!("{}: {}", self.label, value))
+    }
+}
+
+fn subscribe<T, F>(
+    topic: &str,
+    value: Arc<RwLock<Option<String>>>,
+    format: F,
+) -> Option<Subscription>
+where
+    T: rosrust::Message,
+    F: Fn(T) -> String + Send + 'static,
+{
+    let cb_value = value.clone();
+    match source::subscribe(topic, 1, move |msg: T| {
+        *cb_value.write().unwrap() = Some(format(msg));
+    }) {
+        Ok(subscriber) => Some(subscriber),
+        Err(e) => {
+            *value.write().unwrap() = Some(format!("cannot subscribe to {}: {}", topic, e));
+            None
+        }
+    }
+}
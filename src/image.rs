@@ -1,92 +1,384 @@
-use crate::config::ImageListenerConfig;
-use byteorder::{ByteOrder, LittleEndian};
-use image::{imageops, DynamicImage, ImageBuffer, Rgb, RgbImage, RgbaImage};
-use rosrust;
+use crate::config::{Colormap, ImageListenerConfig};
+use crate::source::{self, Subscription};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use colorgrad;
+use image::{imageops, DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb, Rgba, RgbaImage};
 use rosrust_msg;
 use std::sync::{Arc, RwLock};
 
-// remap a value from range min_val - max_val to 0 - 255
-fn remap_u8(val: f64, min_val: f64, max_val: f64) -> u8 {
-    ((val - min_val) * (u8::MAX as f64 / (max_val - min_val))) as u8
+/// Reads the pixels of an image message, taking into account the row stride and the endianness.
+/// Data missing from the message is read as 0.
+struct PixelReader<'a> {
+    data: &'a [u8],
+    step: usize,
+    bytes_per_pixel: usize,
+    is_bigendian: bool,
 }
 
-fn bgr2rgb(bgr_img: &RgbImage) -> RgbImage {
-    ImageBuffer::from_fn(bgr_img.width(), bgr_img.height(), |x, y| {
-        let bgr = bgr_img.get_pixel(x, y);
-        Rgb([bgr[2], bgr[1], bgr[0]])
+impl<'a> PixelReader<'a> {
+    fn offset(&self, x: u32, y: u32) -> usize {
+        y as usize * self.step + x as usize * self.bytes_per_pixel
+    }
+
+    fn bytes(&self, x: u32, y: u32, byte: usize, len: usize) -> Option<&[u8]> {
+        let start = self.offset(x, y) + byte;
+        self.data.get(start..start + len)
+    }
+
+    fn u8(&self, x: u32, y: u32, channel: usize) -> u8 {
+        self.bytes(x, y, channel, 1).map_or(0, |b| b[0])
+    }
+
+    fn u16(&self, x: u32, y: u32, channel: usize) -> u16 {
+        match self.bytes(x, y, 2 * channel, 2) {
+            Some(b) if self.is_bigendian => BigEndian::read_u16(b),
+            Some(b) => LittleEndian::read_u16(b),
+            None => 0,
+        }
+    }
+
+    fn f32(&self, x: u32, y: u32) -> f32 {
+        match self.bytes(x, y, 0, 4) {
+            Some(b) if self.is_bigendian => BigEndian::read_f32(b),
+            Some(b) => LittleEndian::read_f32(b),
+            None => f32::NAN,
+        }
+    }
+
+    /// Reads a channel of 8 or 16 bits as 8 bits.
+    fn channel(&self, x: u32, y: u32, channel: usize, sixteen_bits: bool) -> u8 {
+        if sixteen_bits {
+            (self.u16(x, y, channel) >> 8) as u8
+        } else {
+            self.u8(x, y, channel)
+        }
+    }
+}
+
+fn bytes_per_pixel(encoding: &str) -> Option<usize> {
+    match encoding {
+        "8UC1" | "mono8" => Some(1),
+        "16UC1" | "mono16" | "yuv422" | "yuv422_yuy2" => Some(2),
+        "8UC3" | "rgb8" | "bgr8" => Some(3),
+        "8UC4" | "rgba8" | "bgra8" | "32FC1" => Some(4),
+        "16UC3" | "rgb16" | "bgr16" => Some(6),
+        "16UC4" | "rgba16" | "bgra16" => Some(8),
+        e if bayer_pattern(e).is_some() && e.ends_with("16") => Some(2),
+        e if bayer_pattern(e).is_some() && e.ends_with('8') => Some(1),
+        _ => None,
+    }
+}
+
+/// Returns the color filter pattern of a bayer encoding, e.g. "rggb" for "bayer_rggb8".
+fn bayer_pattern(encoding: &str) -> Option<&str> {
+    let pattern = encoding.strip_prefix("bayer_")?.get(0..4)?;
+    match pattern {
+        "rggb" | "bggr" | "gbrg" | "grbg" => Some(pattern),
+        _ => None,
+    }
+}
+
+fn yuv2rgb(y: u8, u: u8, v: u8) -> Rgba<u8> {
+    let (y, u, v) = (y as f64, u as f64 - 128.0, v as f64 - 128.0);
+    let clamp = |val: f64| val.max(0.0).min(255.0) as u8;
+    Rgba([
+        clamp(y + 1.402 * v),
+        clamp(y - 0.344136 * u - 0.714136 * v),
+        clamp(y + 1.772 * u),
+        u8::MAX,
+    ])
+}
+
+fn read_color(reader: &PixelReader, width: u32, height: u32, encoding: &str) -> RgbaImage {
+    let sixteen_bits = reader.bytes_per_pixel >= 6;
+    let channels = if sixteen_bits {
+        reader.bytes_per_pixel / 2
+    } else {
+        reader.bytes_per_pixel
+    };
+    let bgr = encoding.starts_with("bgr");
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let c = |i| reader.channel(x, y, i, sixteen_bits);
+        let alpha = if channels == 4 { c(3) } else { u8::MAX };
+        if bgr {
+            Rgba([c(2), c(1), c(0), alpha])
+        } else {
+            Rgba([c(0), c(1), c(2), alpha])
+        }
+    })
+}
+
+fn read_yuv422(reader: &PixelReader, width: u32, height: u32, encoding: &str) -> RgbaImage {
+    // Each group of 4 bytes contains 2 pixels: UYVY for yuv422, YUYV for yuv422_yuy2
+    let (y_byte, u_byte, v_byte) = if encoding == "yuv422" {
+        (1, 0, 2)
+    } else {
+        (0, 1, 3)
+    };
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let pair = x - x % 2;
+        yuv2rgb(
+            reader.u8(pair, y, y_byte + 2 * (x % 2) as usize),
+            reader.u8(pair, y, u_byte),
+            reader.u8(pair, y, v_byte),
+        )
     })
 }
 
-fn read_img_msg(img_msg: rosrust_msg::sensor_msgs::Image) -> DynamicImage {
-    match img_msg.encoding.as_ref() {
-        "8UC1" | "mono8" => DynamicImage::ImageLuma8(
-            ImageBuffer::from_raw(img_msg.width, img_msg.height, img_msg.data).unwrap(),
+fn read_bayer(reader: &PixelReader, width: u32, height: u32, pattern: &str) -> RgbaImage {
+    let sixteen_bits = reader.bytes_per_pixel == 2;
+    let pattern: Vec<char> = pattern.chars().collect();
+    // Each pixel takes the colors of the 2x2 cell of the filter it belongs to
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (cell_x, cell_y) = (x - x % 2, y - y % 2);
+        let mut rgb = [0u32; 3];
+        let mut count = [0u32; 3];
+        for (i, color) in pattern.iter().enumerate() {
+            let px = (cell_x + i as u32 % 2).min(width - 1);
+            let py = (cell_y + i as u32 / 2).min(height - 1);
+            let c = match color {
+                'r' => 0,
+                'g' => 1,
+                _ => 2,
+            };
+            rgb[c] += reader.channel(px, py, 0, sixteen_bits) as u32;
+            count[c] += 1;
+        }
+        Rgba([
+            (rgb[0] / count[0]) as u8,
+            (rgb[1] / count[1]) as u8,
+            (rgb[2] / count[2]) as u8,
+            u8::MAX,
+        ])
+    })
+}
+
+/// Depth values in meters of a depth image, before its rotation.
+#[derive(Clone)]
+pub struct DepthImage {
+    pub width: u32,
+    pub height: u32,
+    pub depths: Vec<f64>,
+}
+
+impl DepthImage {
+    /// Returns the depth of a pixel of the image rotated by `rotation` degrees.
+    pub fn get_rotated(&self, x: u32, y: u32, rotation: i64) -> Option<f64> {
+        let (x, y) = match rotation {
+            90 => (y, self.height.checked_sub(x + 1)?),
+            180 => (
+                self.width.checked_sub(x + 1)?,
+                self.height.checked_sub(y + 1)?,
+            ),
+            270 => (self.width.checked_sub(y + 1)?, x),
+            _ => (x, y),
+        };
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.depths.get((y * self.width + x) as usize).cloned()
+    }
+}
+
+/// Converts depth values in meters to an image, using the configured range and colormap.
+/// Invalid depths are black.
+fn depth_to_img(depth: &DepthImage, config: &ImageListenerConfig) -> DynamicImage {
+    let (depths, width, height) = (&depth.depths, depth.width, depth.height);
+    let valid = depths.iter().filter(|d| d.is_finite() && **d > 0.0);
+    let (min_depth, max_depth) = match config.depth_range {
+        Some(range) => range,
+        None => (
+            valid.clone().cloned().fold(f64::MAX, f64::min),
+            valid.cloned().fold(f64::MIN, f64::max),
         ),
-        "8UC3" | "rgb8" | "bgr8" => {
-            let mut img: RgbImage =
-                ImageBuffer::from_raw(img_msg.width, img_msg.height, img_msg.data).unwrap();
-            if img_msg.encoding == "bgr8" {
-                img = bgr2rgb(&img)
+    };
+    let grad = colorgrad::turbo();
+    DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+        let depth = depths
+            .get((y * width + x) as usize)
+            .cloned()
+            .unwrap_or(f64::NAN);
+        if !depth.is_finite() || depth <= 0.0 {
+            return Rgb([0, 0, 0]);
+        }
+        let ratio = if max_depth > min_depth {
+            ((depth - min_depth) / (max_depth - min_depth))
+                .max(0.0)
+                .min(1.0)
+        } else {
+            0.0
+        };
+        match config.colormap {
+            Colormap::Turbo => {
+                let c = grad.at(ratio).to_rgba8();
+                Rgb([c[0], c[1], c[2]])
+            }
+            Colormap::Gray => {
+                let gray = (ratio * u8::MAX as f64) as u8;
+                Rgb([gray, gray, gray])
             }
-            DynamicImage::ImageRgb8(img)
         }
-        "16UC1" | "mono16" => DynamicImage::ImageLuma8(
-            ImageBuffer::from_raw(img_msg.width, img_msg.height, read_u16(&img_msg.data)).unwrap(),
-        ),
-        "32FC1" => DynamicImage::ImageLuma8(
-            ImageBuffer::from_raw(img_msg.width, img_msg.height, read_f32(&img_msg.data)).unwrap(),
-        ),
-        _ => panic!("Image encoding {:?} not supported", img_msg.encoding),
+    }))
+}
+
+/// Reads a mono16 image, scaled to the range of its values.
+fn read_mono16(reader: &PixelReader, width: u32, height: u32) -> DynamicImage {
+    let mut values = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            values.push(reader.u16(x, y, 0));
+        }
     }
+    let min = values.iter().cloned().min().unwrap_or(0) as u32;
+    let max = values.iter().cloned().max().unwrap_or(0) as u32;
+    let range = (max - min).max(1);
+    let pixels = values
+        .iter()
+        .map(|val| ((*val as u32 - min) * u8::MAX as u32 / range) as u8)
+        .collect();
+    DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, pixels).unwrap())
 }
 
-fn read_f32(vec: &Vec<u8>) -> Vec<u8> {
-    let mut vals: Vec<f32> = Vec::with_capacity(vec.len() / 4);
-    let mut max_val = f32::MIN;
-    let mut min_val = f32::MAX;
-    for elem in vec.chunks(4) {
-        let val = LittleEndian::read_f32(&elem);
-        vals.push(val);
-        if val > max_val {
-            max_val = val;
+/// Converts an image message to an image, and to depth values if it is a depth image.
+/// Returns an error if the encoding is not supported.
+fn read_img_msg(
+    img_msg: rosrust_msg::sensor_msgs::Image,
+    config: &ImageListenerConfig,
+) -> Result<(DynamicImage, Option<DepthImage>), String> {
+    let encoding = img_msg.encoding.as_str();
+    let (width, height) = (img_msg.width, img_msg.height);
+    let reader = PixelReader {
+        data: &img_msg.data,
+        step: img_msg.step as usize,
+        bytes_per_pixel: bytes_per_pixel(encoding)
+            .ok_or_else(|| format!("Unsupported encoding {}", encoding))?,
+        is_bigendian: img_msg.is_bigendian != 0,
+    };
+    let img = match encoding {
+        "8UC1" | "mono8" => {
+            DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |x, y| {
+                Luma([reader.u8(x, y, 0)])
+            }))
         }
-        if val < min_val {
-            min_val = val;
+        "mono16" => read_mono16(&reader, width, height),
+        "16UC1" | "32FC1" => {
+            // Depth images are in millimeters if 16 bits, in meters if float
+            let mut depths = Vec::with_capacity((width * height) as usize);
+            for y in 0..height {
+                for x in 0..width {
+                    depths.push(if encoding == "16UC1" {
+                        reader.u16(x, y, 0) as f64 / 1000.0
+                    } else {
+                        reader.f32(x, y) as f64
+                    });
+                }
+            }
+            let depth = DepthImage {
+                width,
+                height,
+                depths,
+            };
+            return Ok((depth_to_img(&depth, config), Some(depth)));
         }
+        "yuv422" | "yuv422_yuy2" => {
+            DynamicImage::ImageRgba8(read_yuv422(&reader, width, height, encoding))
+        }
+        e => match bayer_pattern(e) {
+            Some(pattern) => DynamicImage::ImageRgba8(read_bayer(&reader, width, height, pattern)),
+            None => DynamicImage::ImageRgba8(read_color(&reader, width, height, encoding)),
+        },
+    };
+    Ok((img, None))
+}
+
+fn read_compressed_img_msg(
+    img_msg: rosrust_msg::sensor_msgs::CompressedImage,
+) -> Option<DynamicImage> {
+    image::load_from_memory(&img_msg.data).ok()
+}
+
+/// Size of the header prepended by compressed_depth_image_transport to the PNG data.
+const COMPRESSED_DEPTH_HEADER_SIZE: usize = 12;
+
+fn read_compressed_depth_msg(
+    img_msg: rosrust_msg::sensor_msgs::CompressedImage,
+    config: &ImageListenerConfig,
+) -> Option<(DynamicImage, Option<DepthImage>)> {
+    if img_msg.data.len() <= COMPRESSED_DEPTH_HEADER_SIZE {
+        return None;
     }
-    let mut bytes: Vec<u8> = Vec::with_capacity(vals.len());
-    for val in vals {
-        bytes.push(remap_u8(val as f64, min_val as f64, max_val as f64));
-    }
-    bytes
+    // The header contains the compression format and the parameters of the depth quantization
+    let depth_quant_a = LittleEndian::read_f32(&img_msg.data[4..8]) as f64;
+    let depth_quant_b = LittleEndian::read_f32(&img_msg.data[8..12]) as f64;
+    let png = image::load_from_memory_with_format(
+        &img_msg.data[COMPRESSED_DEPTH_HEADER_SIZE..],
+        ImageFormat::Png,
+    )
+    .ok()?
+    .into_luma16();
+    let depths: Vec<f64> = if img_msg.format.starts_with("32FC1") {
+        // Float depth is quantized as inverse depth, 0 means invalid
+        png.pixels()
+            .map(|p| match p[0] {
+                0 => f64::NAN,
+                val => depth_quant_a / (val as f64 - depth_quant_b),
+            })
+            .collect()
+    } else {
+        png.pixels().map(|p| p[0] as f64 / 1000.0).collect()
+    };
+    let depth = DepthImage {
+        width: png.width(),
+        height: png.height(),
+        depths,
+    };
+    Some((depth_to_img(&depth, config), Some(depth)))
+}
+
+/// Data shared with the callbacks of the subscribers.
+#[derive(Clone)]
+struct FrameTarget {
+    img: Arc<RwLock<RgbaImage>>,
+    depth: Arc<RwLock<Option<DepthImage>>>,
+    rotation: Arc<RwLock<i64>>,
+    paused: Arc<RwLock<bool>>,
+    error: Arc<RwLock<Option<String>>>,
 }
 
-fn read_u16(vec: &Vec<u8>) -> Vec<u8> {
-    let mut vals: Vec<u16> = Vec::with_capacity(vec.len() / 2);
-    let mut max_val = u16::MIN;
-    let mut min_val = u16::MAX;
-    for elem in vec.chunks(2) {
-        let val = LittleEndian::read_u16(&elem);
-        vals.push(val);
-        if val > max_val {
-            max_val = val;
+impl FrameTarget {
+    /// Rotates the received image and makes it available for drawing, unless paused.
+    fn store(&self, img: DynamicImage, depth: Option<DepthImage>) {
+        if *self.paused.read().unwrap() {
+            return;
         }
-        if val < min_val {
-            min_val = val;
+        let mut img = img.to_rgba8();
+        match *self.rotation.read().unwrap() {
+            90 => img = imageops::rotate90(&img),
+            180 => img = imageops::rotate180(&img),
+            270 => img = imageops::rotate270(&img),
+            _ => (),
         }
+        *self.img.write().unwrap() = img;
+        *self.depth.write().unwrap() = depth;
+        *self.error.write().unwrap() = None;
     }
-    let mut bytes: Vec<u8> = Vec::with_capacity(vals.len());
-    for val in vals {
-        bytes.push(remap_u8(val as f64, min_val as f64, max_val as f64));
+
+    /// Keeps the last image, and reports why the received one was skipped.
+    fn skip(&self, error: String) {
+        *self.error.write().unwrap() = Some(error);
     }
-    bytes
 }
 
 pub struct ImageListener {
     pub config: ImageListenerConfig,
     pub img: Arc<RwLock<RgbaImage>>,
-    _subscriber: Option<rosrust::Subscriber>,
+    pub depth: Arc<RwLock<Option<DepthImage>>>,
+    pub camera_info: Arc<RwLock<Option<rosrust_msg::sensor_msgs::CameraInfo>>>,
+    pub error: Arc<RwLock<Option<String>>>, // Why the last frame could not be shown
+    _subscriber: Option<Subscription>,
+    _info_subscriber: Option<Subscription>,
     _rotation: Arc<RwLock<i64>>,
+    _paused: Arc<RwLock<bool>>,
 }
 
 impl ImageListener {
@@ -96,32 +388,76 @@ impl ImageListener {
         ImageListener {
             config,
             img,
+            depth: Arc::new(RwLock::new(None)),
+            camera_info: Arc::new(RwLock::new(None)),
+            error: Arc::new(RwLock::new(None)),
             _subscriber: None,
+            _info_subscriber: None,
             _rotation: Arc::new(RwLock::new(default_rotation)),
+            _paused: Arc::new(RwLock::new(false)),
         }
     }
 
     pub fn setup_sub(&mut self) {
-        let cb_img = self.img.clone();
-        let cb_rotation = self._rotation.clone();
-        let sub = rosrust::subscribe(
-            &self.config.topic,
+        let target = FrameTarget {
+            img: self.img.clone(),
+            depth: self.depth.clone(),
+            rotation: self._rotation.clone(),
+            paused: self._paused.clone(),
+            error: self.error.clone(),
+        };
+        let cb_config = self.config.clone();
+        let sub = match self.config.transport.as_str() {
+            "compressed" | "compressedDepth" => source::subscribe(
+                &format!("{}/{}", self.config.topic, self.config.transport),
+                1,
+                move |img_msg: rosrust_msg::sensor_msgs::CompressedImage| {
+                    let frame = if cb_config.transport == "compressedDepth" {
+                        read_compressed_depth_msg(img_msg, &cb_config)
+                    } else {
+                        read_compressed_img_msg(img_msg).map(|img| (img, None))
+                    };
+                    match frame {
+                        Some((img, depth)) => target.store(img, depth),
+                        None => target.skip(format!("Cannot decode {} image", cb_config.transport)),
+                    }
+                },
+            ),
+            _ => source::subscribe(
+                &self.config.topic,
+                1,
+                move |img_msg: rosrust_msg::sensor_msgs::Image| {
+                    let frame = read_img_msg(img_msg, &cb_config);
+                    match frame {
+                        Ok((img, depth)) => target.store(img, depth),
+                        Err(e) => target.skip(e),
+                    }
+                },
+            ),
+        }
+        .unwrap();
+        self._subscriber = Some(sub);
+
+        let cb_info = self.camera_info.clone();
+        self._info_subscriber = source::subscribe(
+            &self.camera_info_topic(),
             1,
-            move |img_msg: rosrust_msg::sensor_msgs::Image| {
-                let mut img = read_img_msg(img_msg).to_rgba8();
-                let rot = cb_rotation.read().unwrap();
-                match *rot {
-                    90 => img = imageops::rotate90(&img),
-                    180 => img = imageops::rotate180(&img),
-                    270 => img = imageops::rotate270(&img),
-                    _ => (),
-                }
-                let mut cb_img = cb_img.write().unwrap();
-                *cb_img = img;
+            move |info: rosrust_msg::sensor_msgs::CameraInfo| {
+                *cb_info.write().unwrap() = Some(info);
             },
         )
-        .unwrap();
-        self._subscriber = Some(sub)
+        .ok();
+    }
+
+    /// Returns the configured camera info topic, or the one in the namespace of the image.
+    pub fn camera_info_topic(&self) -> String {
+        if !self.config.camera_info_topic.is_empty() {
+            return self.config.camera_info_topic.clone();
+        }
+        match self.config.topic.rsplit_once('/') {
+            Some((namespace, _)) => format!("{}/camera_info", namespace),
+            None => "camera_info".to_string(),
+        }
     }
 
     pub fn is_active(&self) -> bool {
@@ -134,6 +470,16 @@ impl ImageListener {
 
     pub fn deactivate(&mut self) {
         self._subscriber = None;
+        self._info_subscriber = None;
+    }
+
+    /// Stops updating the image, e.g. to inspect it.
+    pub fn set_paused(&mut self, paused: bool) {
+        *self._paused.write().unwrap() = paused;
+    }
+
+    pub fn rotation(&self) -> i64 {
+        *self._rotation.read().unwrap()
     }
 
     pub fn rotate(&mut self, angle: i64) {
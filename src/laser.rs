@@ -1,58 +1,63 @@
 use crate::config::ListenerConfigColor;
+use crate::source::{self, Subscription};
+use crate::tf::TfListener;
 use crate::transformation;
+use nalgebra::geometry::Point3;
 use std::sync::{Arc, RwLock};
 
-use rosrust;
-use rustros_tf;
-
 pub struct LaserListener {
     pub config: ListenerConfigColor,
     pub points: Arc<RwLock<Vec<(f64, f64)>>>,
-    _tf_listener: Arc<rustros_tf::TfListener>,
+    pub points_3d: Arc<RwLock<Vec<Point3<f64>>>>, // Used to project the scan into images
+    _tf_listener: Arc<TfListener>,
     _static_frame: String,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl LaserListener {
     pub fn new(
         config: ListenerConfigColor,
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         static_frame: String,
     ) -> LaserListener {
         let scan_points = Arc::new(RwLock::new(Vec::<(f64, f64)>::new()));
         let cb_scan_points = scan_points.clone();
+        let scan_points_3d = Arc::new(RwLock::new(Vec::<Point3<f64>>::new()));
+        let cb_scan_points_3d = scan_points_3d.clone();
         let str_ = static_frame.clone();
 
         let local_listener = tf_listener.clone();
-        let laser_sub = rosrust::subscribe(
+        let laser_sub = source::subscribe(
             &config.topic,
             2,
             move |scan: rosrust_msg::sensor_msgs::LaserScan| {
                 let mut points: Vec<(f64, f64)> = Vec::new();
+                let mut points_3d: Vec<Point3<f64>> = Vec::new();
                 let res = local_listener.lookup_transform(
                     &str_,
                     &scan.header.frame_id,
                     scan.header.stamp,
                 );
-                match &res {
-                    Ok(res) => res,
+                let iso = match &res {
+                    Ok(res) => transformation::ros_transform_to_isometry(&res.transform),
                     Err(_e) => return,
                 };
                 for (i, range) in scan.ranges.iter().enumerate() {
                     let angle = scan.angle_min + i as f32 * scan.angle_increment;
-                    let pt = transformation::transform_relative_pt(
-                        &res.as_ref().unwrap().transform,
-                        (
-                            *range as f64 * angle.cos() as f64,
-                            *range as f64 * angle.sin() as f64,
-                        ),
-                    );
+                    let pt = iso.transform_point(&Point3::new(
+                        *range as f64 * angle.cos() as f64,
+                        *range as f64 * angle.sin() as f64,
+                        0.,
+                    ));
                     if range > &scan.range_min {
-                        points.push(pt);
+                        points.push((pt.x, pt.y));
+                        points_3d.push(pt);
                     }
                 }
                 let mut cb_scan_points = cb_scan_points.write().unwrap();
                 *cb_scan_points = points;
+                let mut cb_scan_points_3d = cb_scan_points_3d.write().unwrap();
+                *cb_scan_points_3d = points_3d;
             },
         )
         .unwrap();
@@ -60,6 +65,7 @@ impl LaserListener {
         LaserListener {
             config,
             points: scan_points,
+            points_3d: scan_points_3d,
             _tf_listener: tf_listener.clone(),
             _static_frame: static_frame.to_string(),
             _subscriber: laser_sub,
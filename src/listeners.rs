@@ -8,6 +8,7 @@ use crate::marker;
 use crate::pointcloud;
 use crate::polygon;
 use crate::pose;
+use crate::tf::TfListener;
 
 use std::sync::Arc;
 
@@ -20,11 +21,12 @@ pub struct Listeners {
     pub pointclouds: Vec<pointcloud::PointCloud2Listener>,
     pub polygons: Vec<polygon::PolygonListener>,
     pub paths: Vec<pose::PathListener>,
+    pub topics: Vec<String>, // Topics of all the listeners
 }
 
 impl Listeners {
     pub fn new(
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         static_frame: String,
         laser_topics: Vec<ListenerConfigColor>,
         marker_topics: Vec<ListenerConfig>,
@@ -36,6 +38,27 @@ impl Listeners {
         polygon_stamped_topics: Vec<ListenerConfigColor>,
         path_topics: Vec<PoseListenerConfig>,
     ) -> Listeners {
+        let topics = laser_topics
+            .iter()
+            .chain(polygon_stamped_topics.iter())
+            .map(|config| config.topic.clone())
+            .chain(
+                marker_topics
+                    .iter()
+                    .chain(marker_array_topics.iter())
+                    .map(|config| config.topic.clone()),
+            )
+            .chain(map_topics.iter().map(|config| config.topic.clone()))
+            .chain(
+                pose_stamped_topics
+                    .iter()
+                    .chain(pose_array_topics.iter())
+                    .chain(path_topics.iter())
+                    .map(|config| config.topic.clone()),
+            )
+            .chain(pointcloud2_topics.iter().map(|config| config.topic.clone()))
+            .collect();
+
         let mut lasers: Vec<laser::LaserListener> = Vec::new();
         for laser_config in laser_topics {
             lasers.push(laser::LaserListener::new(
@@ -102,6 +125,7 @@ impl Listeners {
             pointclouds,
             polygons,
             paths,
+            topics,
         }
     }
 }
@@ -1,15 +1,27 @@
 mod app;
 mod app_modes;
+mod bag;
 mod config;
+mod diagnostics;
+mod dynamic;
 mod footprint;
+mod hud;
 mod image;
 mod laser;
 mod listeners;
 mod map;
+mod map_file;
 mod marker;
+mod move_base;
+mod player;
 mod pointcloud;
 mod polygon;
 mod pose;
+mod recorder;
+mod rosout;
+mod service;
+mod source;
+mod tf;
 mod transformation;
 use futures::{future::FutureExt, select, StreamExt};
 use futures_timer::Delay;
@@ -26,8 +38,8 @@ use crossterm::{
 };
 use dialoguer::Confirm;
 use rosrust;
-use rustros_tf::TfListener;
 use std::error::Error;
+use tf::TfListener;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -47,13 +59,55 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .long_help("How long to wait for the robot pose TF on startup, in seconds.")
                 .value_parser(value_parser!(u64)),
         )
+        .arg(
+            Arg::new("bag")
+                .long("bag")
+                .action(ArgAction::Set)
+                .long_help(
+                    "ROS1 bag to play back, without connecting to ROS. Compressed (bz2 or lz4) bags are not supported.",
+                ),
+        )
+        .arg(
+            Arg::new("map")
+                .long("map")
+                .action(ArgAction::Set)
+                .long_help(
+                    "Map in the format of map_server (YAML and image) to show, without connecting to ROS.",
+                ),
+        )
         .after_help("More documentation can be found at: https://github.com/carzum/termviz")
         .get_matches();
 
-    let conf = config::get_config(matches.get_one("config"))?;
+    let mut conf = config::get_config(matches.get_one("config"))?;
 
-    println!("Connecting to ROS...");
-    rosrust::init("termviz");
+    let player = match matches.get_one::<String>("bag") {
+        Some(path) => {
+            println!("Loading bag {}...", path);
+            let bag = bag::Bag::open(path).map_err(|e| format!("Cannot load bag: {}", e))?;
+            Some(player::Player::new(path, bag))
+        }
+        None => None,
+    };
+    let map = match matches.get_one::<String>("map") {
+        Some(path) => {
+            println!("Loading map {}...", path);
+            let grid = map_file::load(path, &conf.fixed_frame)
+                .map_err(|e| format!("Cannot load map: {}", e))?;
+            // The map is shown in the first map layer, which is added if there is none
+            if conf.map_topics.is_empty() {
+                conf.map_topics
+                    .push(config::TermvizConfig::default().map_topics[0].clone());
+            }
+            Some((conf.map_topics[0].topic.clone(), grid))
+        }
+        None => None,
+    };
+    if player.is_some() || map.is_some() {
+        source::set_offline();
+    } else {
+        println!("Connecting to ROS...");
+        rosrust::init("termviz");
+    }
 
     let mut key_to_input: HashMap<KeyCode, String> = conf
         .key_mapping
@@ -61,10 +115,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .map(|(v, k)| match k.as_str() {
             "Enter" => (KeyCode::Enter, v.clone()),
             "Esc" => (KeyCode::Esc, v.clone()),
+            "Tab" => (KeyCode::Tab, v.clone()),
+            "BackTab" => (KeyCode::BackTab, v.clone()),
             _ => (KeyCode::Char(k.chars().next().unwrap()), v.clone()),
         })
         .collect();
-    for i in 0..9 {
+    for i in 0..10 {
         key_to_input.insert(
             KeyCode::Char(std::char::from_digit(i, 10).unwrap()),
             i.to_string(),
@@ -74,38 +130,42 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("Starting TF listener");
     let listener = Arc::new(TfListener::new());
 
-    // rustros_tf has no option for a timeout, so we have to do it manually.
-    let mut passed_time = std::time::Duration::ZERO;
-    let max_time = std::time::Duration::from_secs(*matches.get_one::<u64>("tf-wait-time").unwrap());
-    let sleep_time = std::time::Duration::from_millis(100);
-
-    println!("Waiting up to {}s for robot pose...", max_time.as_secs());
-    let robot_pose_available = loop {
-        if listener
-            .lookup_transform(&conf.fixed_frame, &conf.robot_frame, rosrust::Time::new())
-            .is_ok()
-        {
-            break true;
-        }
-        std::thread::sleep(sleep_time);
-        passed_time += sleep_time;
-        if passed_time > max_time {
-            break false;
-        }
-    };
+    // Offline, the robot pose is only known once the playback starts
+    if !source::is_offline() {
+        // rustros_tf has no option for a timeout, so we have to do it manually.
+        let mut passed_time = std::time::Duration::ZERO;
+        let max_time =
+            std::time::Duration::from_secs(*matches.get_one::<u64>("tf-wait-time").unwrap());
+        let sleep_time = std::time::Duration::from_millis(100);
+
+        println!("Waiting up to {}s for robot pose...", max_time.as_secs());
+        let robot_pose_available = loop {
+            if listener
+                .lookup_transform(&conf.fixed_frame, &conf.robot_frame, rosrust::Time::new())
+                .is_ok()
+            {
+                break true;
+            }
+            std::thread::sleep(sleep_time);
+            passed_time += sleep_time;
+            if passed_time > max_time {
+                break false;
+            }
+        };
 
-    if !robot_pose_available {
-        println!(
-            "\n{}\n{}",
-            "Robot pose is not being published on TF!".bold().red(),
-            "termviz will display the robot at the origin of the map and you can set the pose from there."
-        );
-        if !Confirm::new()
-            .with_prompt("\nContinue?")
-            .interact()
-            .unwrap()
-        {
-            Err("Aborting.")?;
+        if !robot_pose_available {
+            println!(
+                "\n{}\n{}",
+                "Robot pose is not being published on TF!".bold().red(),
+                "termviz will display the robot at the origin of the map and you can set the pose from there."
+            );
+            if !Confirm::new()
+                .with_prompt("\nContinue?")
+                .interact()
+                .unwrap()
+            {
+                Err("Aborting.")?;
+            }
         }
     }
 
@@ -113,9 +173,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let rate = Duration::from_millis(1000 / conf.target_framerate as u64);
 
-    let default_app_config = Arc::new(Mutex::new(app::App::new(listener.clone(), conf)));
+    let default_app_config = Arc::new(Mutex::new(app::App::new(listener.clone(), conf, player)));
 
     let mut running_app = default_app_config.lock().unwrap();
+    if let Some((topic, grid)) = map {
+        source::dispatch_message(&topic, &grid);
+    }
 
     let mut terminal = running_app.init_terminal().unwrap();
 
@@ -136,7 +199,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         }
                         if let Event::Key(input) = event {
 
-                            if key_to_input.contains_key(&input.code) {
+                            if running_app.is_editing() {
+                                running_app.handle_text_input(&input.code);
+                            } else if key_to_input.contains_key(&input.code) {
                                 running_app.handle_input(&key_to_input[&input.code]);
                             } else {
                                 running_app.handle_input(&app_modes::input::UNMAPPED.to_string());
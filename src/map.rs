@@ -1,32 +1,35 @@
 use crate::config::MapListenerConfig;
+use crate::source::{self, Subscription};
+use crate::tf::TfListener;
 use crate::transformation;
+use rosrust_msg::nav_msgs::OccupancyGrid;
 use std::sync::{Arc, RwLock};
 
 use nalgebra::geometry::{Isometry3, Point3, Quaternion, Translation3, UnitQuaternion};
 
-use rosrust;
-use rustros_tf;
-
 pub struct MapListener {
     pub config: MapListenerConfig,
     pub points: Arc<RwLock<Vec<(f64, f64)>>>,
-    _tf_listener: Arc<rustros_tf::TfListener>,
+    pub grid: Arc<RwLock<Option<OccupancyGrid>>>, // Last map shown
+    _tf_listener: Arc<TfListener>,
     _static_frame: String,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl MapListener {
     pub fn new(
         config: MapListenerConfig,
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         static_frame: String,
     ) -> MapListener {
         let occ_points = Arc::new(RwLock::new(Vec::<(f64, f64)>::new()));
         let cb_occ_points = occ_points.clone();
+        let grid = Arc::new(RwLock::new(None));
+        let cb_grid = grid.clone();
         let str_ = static_frame.clone();
         let local_listener = tf_listener.clone();
         let threshold = config.threshold.clone();
-        let _map_sub = rosrust::subscribe(
+        let _map_sub = source::subscribe(
             &config.topic,
             1,
             move |map: rosrust_msg::nav_msgs::OccupancyGrid| {
@@ -72,6 +75,7 @@ impl MapListener {
                 }
                 let mut cb_occ_points = cb_occ_points.write().unwrap();
                 *cb_occ_points = points;
+                *cb_grid.write().unwrap() = Some(map);
             },
         )
         .unwrap();
@@ -79,6 +83,7 @@ impl MapListener {
         MapListener {
             config,
             points: occ_points,
+            grid,
             _tf_listener: tf_listener,
             _static_frame: static_frame.to_string(),
             _subscriber: _map_sub,
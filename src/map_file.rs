@@ -0,0 +1,130 @@
+//! Maps in the format of map_server: a YAML file with the metadata and an image (e.g., PGM).
+use crate::transformation::{iso2d_to_ros_pose, iso3d_to_iso2d, ros_pose_to_isometry};
+use nalgebra::{Isometry2, Vector2};
+use rosrust_msg::nav_msgs::OccupancyGrid;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::Path;
+
+/// Occupancy values up to which a cell is saved as free, and from which it is saved as occupied,
+/// like map_saver.
+const SAVE_FREE_THRESHOLD: i8 = 25;
+const SAVE_OCCUPIED_THRESHOLD: i8 = 65;
+
+fn default_mode() -> String {
+    "trinary".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct MapMetadata {
+    image: String, // Relative to the YAML file, if not absolute
+    resolution: f64,
+    origin: [f64; 3], // x, y and yaw
+    #[serde(default)]
+    negate: i32,
+    occupied_thresh: f64,
+    free_thresh: f64,
+    #[serde(default = "default_mode")]
+    mode: String,
+}
+
+/// Converts a pixel to an occupancy value, like map_server.
+fn occupancy(value: u8, metadata: &MapMetadata) -> i8 {
+    if metadata.mode == "raw" {
+        return value as i8;
+    }
+    let p = if metadata.negate != 0 {
+        value as f64 / 255.0
+    } else {
+        (255 - value) as f64 / 255.0
+    };
+    if p > metadata.occupied_thresh {
+        100
+    } else if p < metadata.free_thresh {
+        0
+    } else if metadata.mode == "scale" {
+        (99.0 * (p - metadata.free_thresh) / (metadata.occupied_thresh - metadata.free_thresh))
+            as i8
+    } else {
+        -1
+    }
+}
+
+/// Loads a map, expressed in the given frame.
+pub fn load(path: &str, frame_id: &str) -> Result<OccupancyGrid, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let metadata: MapMetadata =
+        serde_yaml::from_reader(file).map_err(|e| format!("{}: {}", path, e))?;
+    let image_path = Path::new(path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(&metadata.image);
+    let img = image::open(&image_path)
+        .map_err(|e| format!("{}: {}", image_path.display(), e))?
+        .to_luma8();
+
+    let mut grid = OccupancyGrid::default();
+    grid.header.frame_id = frame_id.to_string();
+    grid.info.resolution = metadata.resolution as f32;
+    grid.info.width = img.width();
+    grid.info.height = img.height();
+    grid.info.origin = iso2d_to_ros_pose(&Isometry2::new(
+        Vector2::new(metadata.origin[0], metadata.origin[1]),
+        metadata.origin[2],
+    ));
+    // The first row of the grid is the bottom one of the image
+    grid.data = img
+        .rows()
+        .rev()
+        .flatten()
+        .map(|pixel| occupancy(pixel[0], &metadata))
+        .collect();
+    Ok(grid)
+}
+
+/// Converts an occupancy value to a pixel, like map_saver.
+fn pixel(value: i8) -> u8 {
+    if (0..=SAVE_FREE_THRESHOLD).contains(&value) {
+        254
+    } else if value >= SAVE_OCCUPIED_THRESHOLD {
+        0
+    } else {
+        205
+    }
+}
+
+/// Saves a map as `<name>.yaml` and `<name>.pgm`, returning the path of the YAML file.
+pub fn save(grid: &OccupancyGrid, name: &str) -> Result<String, String> {
+    let width = grid.info.width as usize;
+    let height = grid.info.height as usize;
+    if grid.data.len() < width * height {
+        return Err("the map has fewer cells than its size".to_string());
+    }
+    let image_path = format!("{}.pgm", name);
+    let mut pgm = format!(
+        "P5\n# CREATOR: termviz {:.3} m/pix\n{} {}\n255\n",
+        grid.info.resolution, width, height
+    )
+    .into_bytes();
+    // The first row of the image is the last one of the grid
+    for row in grid.data[..width * height].chunks(width.max(1)).rev() {
+        pgm.extend(row.iter().map(|value| pixel(*value)));
+    }
+    std::fs::write(&image_path, pgm).map_err(|e| format!("{}: {}", image_path, e))?;
+
+    let origin = iso3d_to_iso2d(&ros_pose_to_isometry(&grid.info.origin));
+    let yaml_path = format!("{}.yaml", name);
+    let mut file =
+        std::fs::File::create(&yaml_path).map_err(|e| format!("{}: {}", yaml_path, e))?;
+    write!(
+        file,
+        "image: {}\nresolution: {}\norigin: [{}, {}, {}]\nnegate: 0\noccupied_thresh: 0.65\nfree_thresh: 0.196\n",
+        Path::new(&image_path).file_name().unwrap().to_string_lossy(),
+        grid.info.resolution,
+        origin.translation.x,
+        origin.translation.y,
+        origin.rotation.angle()
+    )
+    .map_err(|e| format!("{}: {}", yaml_path, e))?;
+    Ok(yaml_path)
+}
@@ -4,13 +4,14 @@
 //! This module allows to subsribe to topics that publish them and project them into the
 //! 2D plane. Finally, it takes care of their lifecycle: ADD, DELETE and timeout.
 use crate::config::ListenerConfig;
+use crate::source::{self, Subscription};
+use crate::tf::TfListener;
 use nalgebra::base::Vector3;
 use nalgebra::geometry::Isometry3;
 use std::collections::HashMap;
 use std::f64::consts::PI;
 use std::sync::{Arc, Mutex, RwLock};
 
-use rosrust;
 use rustros_tf::transforms::nalgebra::geometry::Point3;
 use rustros_tf::transforms::{isometry_from_pose, isometry_from_transform};
 
@@ -19,6 +20,8 @@ use tui::widgets::canvas::Line;
 
 struct TermvizMarker {
     pub lines: Vec<Line>,
+    pub points: Vec<(f64, f64, f64)>, // Position or points of the marker in the static frame
+    pub color: Color,
     pub id: i32,
 }
 
@@ -509,8 +512,19 @@ fn parse_marker_msg(
         _ => Vec::new(),
     };
 
+    let points = if msg.points.is_empty() {
+        vec![iso * Point3::origin()]
+    } else {
+        msg.points
+            .iter()
+            .map(|pt| iso * Point3::new(pt.x, pt.y, pt.z))
+            .collect()
+    };
+
     TermvizMarker {
         lines: res,
+        points: points.iter().map(|pt| (pt.x, pt.y, pt.z)).collect(),
+        color: color,
         id: msg.id,
     }
 }
@@ -524,14 +538,11 @@ fn parse_marker_msg(
 struct TermvizMarkerContainer {
     markers: HashMap<String, HashMap<i32, TermvizMarker>>,
     static_frame: String,
-    tf_listener: Arc<rustros_tf::TfListener>,
+    tf_listener: Arc<TfListener>,
 }
 
 impl TermvizMarkerContainer {
-    pub fn new(
-        tf_listener: Arc<rustros_tf::TfListener>,
-        static_frame: String,
-    ) -> TermvizMarkerContainer {
+    pub fn new(tf_listener: Arc<TfListener>, static_frame: String) -> TermvizMarkerContainer {
         Self {
             markers: HashMap::<String, HashMap<i32, TermvizMarker>>::new(),
             static_frame: static_frame,
@@ -594,6 +605,16 @@ impl TermvizMarkerContainer {
         }
         res
     }
+
+    fn get_points(&self) -> Vec<((f64, f64, f64), Color)> {
+        let mut res = Vec::new();
+        for namespace in self.markers.values() {
+            for marker in namespace.values() {
+                res.extend(marker.points.iter().map(|pt| (*pt, marker.color)));
+            }
+        }
+        res
+    }
 }
 
 /// Class that handles the lifecycle of the markers.
@@ -708,15 +729,19 @@ impl MarkersLifecycle {
     fn get_lines(&self) -> Vec<Line> {
         self.markers_container.write().unwrap().get_lines()
     }
+
+    fn get_points(&self) -> Vec<((f64, f64, f64), Color)> {
+        self.markers_container.read().unwrap().get_points()
+    }
 }
 
 pub struct MarkersListener {
     markers_lifecycle: Arc<RwLock<MarkersLifecycle>>,
-    subscribers: Vec<Arc<Mutex<rosrust::Subscriber>>>,
+    subscribers: Vec<Arc<Mutex<Subscription>>>,
 }
 
 impl MarkersListener {
-    pub fn new(tf_listener: Arc<rustros_tf::TfListener>, static_frame: String) -> MarkersListener {
+    pub fn new(tf_listener: Arc<TfListener>, static_frame: String) -> MarkersListener {
         let marker_container = TermvizMarkerContainer::new(tf_listener, static_frame);
         Self {
             markers_lifecycle: Arc::new(RwLock::new(MarkersLifecycle::new(marker_container))),
@@ -730,6 +755,12 @@ impl MarkersListener {
         markers_container_ref.get_lines()
     }
 
+    /// Gets the positions of all the markers currently active, in the static frame.
+    pub fn get_points(&self) -> Vec<((f64, f64, f64), Color)> {
+        let markers_container_ref = self.markers_lifecycle.read().unwrap();
+        markers_container_ref.get_points()
+    }
+
     /// Adds a subscriber for a marker topic.
     ///
     /// # Arguments
@@ -737,7 +768,7 @@ impl MarkersListener {
     pub fn add_marker_listener(&mut self, config: &ListenerConfig) {
         let markers_container_ref = self.markers_lifecycle.clone();
 
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             2,
             move |msg: rosrust_msg::visualization_msgs::Marker| {
@@ -766,7 +797,7 @@ impl MarkersListener {
     pub fn add_marker_array_listener(&mut self, config: &ListenerConfig) {
         let markers_container_ref = self.markers_lifecycle.clone();
 
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             2,
             move |msg: rosrust_msg::visualization_msgs::MarkerArray| {
@@ -0,0 +1,186 @@
+//! Minimal client for `move_base_msgs/MoveBaseAction` servers.
+//!
+//! ROS1 actions are a set of topics under a common namespace: `goal`, `cancel`, `status`,
+//! `feedback` and `result`. This module implements the client side directly on these topics,
+//! so it works with any server providing the action (move_base, the move_base_flex relay, ...).
+use crate::transformation;
+use nalgebra::Isometry2;
+use std::sync::{Arc, RwLock};
+
+use rosrust;
+use rosrust_msg;
+use rosrust_msg::actionlib_msgs::{GoalID, GoalStatus, GoalStatusArray};
+use rosrust_msg::move_base_msgs::{
+    MoveBaseActionFeedback, MoveBaseActionGoal, MoveBaseActionResult,
+};
+
+/// Returns a human readable name for an actionlib goal status.
+pub fn status_name(status: u8) -> &'static str {
+    match status {
+        GoalStatus::PENDING => "Pending",
+        GoalStatus::ACTIVE => "Active",
+        GoalStatus::PREEMPTED => "Preempted",
+        GoalStatus::SUCCEEDED => "Succeeded",
+        GoalStatus::ABORTED => "Aborted",
+        GoalStatus::REJECTED => "Rejected",
+        GoalStatus::PREEMPTING => "Preempting",
+        GoalStatus::RECALLING => "Recalling",
+        GoalStatus::RECALLED => "Recalled",
+        GoalStatus::LOST => "Lost",
+        _ => "Unknown",
+    }
+}
+
+/// Returns true if the goal status is final, i.e., the server is done with the goal.
+pub fn is_terminal(status: u8) -> bool {
+    match status {
+        GoalStatus::PREEMPTED
+        | GoalStatus::SUCCEEDED
+        | GoalStatus::ABORTED
+        | GoalStatus::REJECTED
+        | GoalStatus::RECALLED
+        | GoalStatus::LOST => true,
+        _ => false,
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct GoalState {
+    pub goal_id: Option<String>,
+    pub target: Option<Isometry2<f64>>,
+    pub status: Option<u8>,
+    pub text: String,
+    pub feedback: Option<Isometry2<f64>>,
+}
+
+impl GoalState {
+    fn update_status(&mut self, status: &GoalStatus) {
+        match &self.goal_id {
+            Some(id) if *id == status.goal_id.id => {
+                self.status = Some(status.status);
+                self.text = status.text.clone();
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns true if a goal was sent and the server did not finish it yet.
+    pub fn is_running(&self) -> bool {
+        match (&self.goal_id, self.status) {
+            (Some(_), Some(status)) => !is_terminal(status),
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct MoveBaseClient {
+    pub action_ns: String,
+    pub state: Arc<RwLock<GoalState>>,
+    goal_counter: u64,
+    goal_pub: rosrust::Publisher<MoveBaseActionGoal>,
+    cancel_pub: rosrust::Publisher<GoalID>,
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl MoveBaseClient {
+    pub fn new(action_ns: &String) -> MoveBaseClient {
+        let state = Arc::new(RwLock::new(GoalState::default()));
+
+        let cb_state = state.clone();
+        let status_sub = rosrust::subscribe(
+            &format!("{}/status", action_ns),
+            1,
+            move |msg: GoalStatusArray| {
+                let mut state = cb_state.write().unwrap();
+                for status in &msg.status_list {
+                    state.update_status(status);
+                }
+            },
+        )
+        .unwrap();
+
+        let cb_state = state.clone();
+        let feedback_sub = rosrust::subscribe(
+            &format!("{}/feedback", action_ns),
+            1,
+            move |msg: MoveBaseActionFeedback| {
+                let mut state = cb_state.write().unwrap();
+                if state.goal_id.as_ref() == Some(&msg.status.goal_id.id) {
+                    state.update_status(&msg.status);
+                    let pose =
+                        transformation::ros_pose_to_isometry(&msg.feedback.base_position.pose);
+                    state.feedback = Some(transformation::iso3d_to_iso2d(&pose));
+                }
+            },
+        )
+        .unwrap();
+
+        let cb_state = state.clone();
+        let result_sub = rosrust::subscribe(
+            &format!("{}/result", action_ns),
+            1,
+            move |msg: MoveBaseActionResult| {
+                cb_state.write().unwrap().update_status(&msg.status);
+            },
+        )
+        .unwrap();
+
+        MoveBaseClient {
+            action_ns: action_ns.clone(),
+            state: state,
+            goal_counter: 0,
+            goal_pub: rosrust::publish(&format!("{}/goal", action_ns), 1).unwrap(),
+            cancel_pub: rosrust::publish(&format!("{}/cancel", action_ns), 1).unwrap(),
+            _subscribers: vec![status_sub, feedback_sub, result_sub],
+        }
+    }
+
+    /// Sends a new goal, replacing the one currently tracked.
+    ///
+    /// # Arguments
+    /// - `pose`: the target pose, in the frame `frame_id`.
+    /// - `frame_id`: the frame of the target pose.
+    pub fn send_goal(&mut self, pose: &Isometry2<f64>, frame_id: &String) {
+        self.goal_counter += 1;
+        let now = rosrust::now();
+        let goal_id = format!(
+            "{}-{}-{}.{}",
+            rosrust::name(),
+            self.goal_counter,
+            now.sec,
+            now.nsec
+        );
+
+        let tf = transformation::iso2d_to_ros(pose);
+        let mut msg = MoveBaseActionGoal::default();
+        msg.header.stamp = now;
+        msg.goal_id.stamp = now;
+        msg.goal_id.id = goal_id.clone();
+        msg.goal.target_pose.header.frame_id = frame_id.clone();
+        msg.goal.target_pose.header.stamp = now;
+        msg.goal.target_pose.pose.position.x = tf.translation.x;
+        msg.goal.target_pose.pose.position.y = tf.translation.y;
+        msg.goal.target_pose.pose.orientation = tf.rotation;
+
+        *self.state.write().unwrap() = GoalState {
+            goal_id: Some(goal_id),
+            target: Some(pose.clone()),
+            ..GoalState::default()
+        };
+        self.goal_pub.send(msg).unwrap();
+    }
+
+    /// Cancels the goal currently tracked, if any.
+    pub fn cancel(&self) {
+        if let Some(goal_id) = &self.state.read().unwrap().goal_id {
+            let mut msg = GoalID::default();
+            msg.id = goal_id.clone();
+            self.cancel_pub.send(msg).unwrap();
+        }
+    }
+
+    pub fn get_state(&self) -> GoalState {
+        self.state.read().unwrap().clone()
+    }
+}
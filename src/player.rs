@@ -0,0 +1,123 @@
+//! Plays back a bag, passing its messages to the offline listeners.
+use crate::bag::Bag;
+use crate::source;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Topics whose messages are all needed after a seek, the others only need their latest message.
+const TF_TOPICS: [&str; 2] = ["/tf", "/tf_static"];
+
+pub struct Player {
+    pub name: String,
+    bag: Bag,
+    start: i64,  // Time of the first message, in nanoseconds
+    end: i64,    // Time of the last message, in nanoseconds
+    time: i64,   // Current time of the playback, in nanoseconds
+    next: usize, // Index of the next message to play
+    pub playing: bool,
+    pub error: Option<String>, // Last error while reading a message
+    last_update: Option<Instant>,
+}
+
+impl Player {
+    pub fn new(path: &str, bag: Bag) -> Player {
+        let (start, end) = bag.time_range();
+        Player {
+            name: std::path::Path::new(path)
+                .file_name()
+                .map_or(path.to_string(), |name| name.to_string_lossy().to_string()),
+            bag,
+            start,
+            end,
+            time: start,
+            next: 0,
+            playing: true,
+            error: None,
+            last_update: None,
+        }
+    }
+
+    /// Advances the playback by the time passed since the last update.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        if self.playing {
+            if let Some(last_update) = self.last_update {
+                let passed = now.duration_since(last_update).as_nanos() as i64;
+                self.time = (self.time + passed).min(self.end);
+            }
+            self.play_until(self.time);
+            if self.time >= self.end {
+                self.playing = false;
+            }
+        }
+        self.last_update = Some(now);
+    }
+
+    /// Passes a message to the listeners of its topic, skipping it if it cannot be read.
+    fn play(&mut self, index: usize) {
+        let msg = &self.bag.messages[index];
+        // The connections of all the messages are checked when opening the bag
+        let connection = &self.bag.connections[&msg.conn];
+        match self.bag.data(msg) {
+            Ok(data) => source::dispatch(&connection.topic, &connection.md5sum, &data),
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn play_until(&mut self, time: i64) {
+        while let Some(msg) = self.bag.messages.get(self.next) {
+            if msg.time > time {
+                break;
+            }
+            self.play(self.next);
+            self.next += 1;
+        }
+    }
+
+    /// Pauses or resumes the playback, restarting it when it reached the end.
+    pub fn toggle_pause(&mut self) {
+        if !self.playing && self.time >= self.end {
+            self.seek(0.0);
+        }
+        self.playing = !self.playing;
+    }
+
+    /// Jumps to a time, in seconds since the start of the bag.
+    ///
+    /// The state of the listeners is cleared, then they get all the transforms until then, and
+    /// the latest message of the other topics.
+    pub fn seek(&mut self, seconds: f64) {
+        let time = (self.start + (seconds * 1e9) as i64)
+            .max(self.start)
+            .min(self.end);
+        let next = self.bag.messages.partition_point(|msg| msg.time <= time);
+        source::reset();
+        let mut transforms = Vec::new();
+        let mut latest: HashMap<u32, usize> = HashMap::new();
+        for (i, msg) in self.bag.messages[..next].iter().enumerate() {
+            let topic = &self.bag.connections[&msg.conn].topic;
+            if TF_TOPICS.contains(&topic.as_str()) {
+                transforms.push(i);
+            } else {
+                latest.insert(msg.conn, i);
+            }
+        }
+        let mut latest: Vec<usize> = latest.into_values().collect();
+        latest.sort();
+        for i in transforms.into_iter().chain(latest) {
+            self.play(i);
+        }
+        self.next = next;
+        self.time = time;
+    }
+
+    /// Time since the start of the bag, in seconds.
+    pub fn elapsed(&self) -> f64 {
+        (self.time - self.start) as f64 / 1e9
+    }
+
+    /// Duration of the bag, in seconds.
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start) as f64 / 1e9
+    }
+}
@@ -1,4 +1,6 @@
 use crate::config::PointCloud2ListenerConfig;
+use crate::source::{self, Subscription};
+use crate::tf::TfListener;
 use byteorder::{ByteOrder, LittleEndian};
 use colorgrad;
 use std::sync::{Arc, RwLock};
@@ -7,15 +9,13 @@ use nalgebra::geometry::Point3;
 use tui::style::Color;
 
 use crate::transformation::ros_transform_to_isometry;
-use rosrust;
-use rustros_tf;
 
 pub struct PointCloud2Listener {
     pub config: PointCloud2ListenerConfig,
     pub points: Arc<RwLock<Vec<ColoredPoint>>>,
-    _tf_listener: Arc<rustros_tf::TfListener>,
+    _tf_listener: Arc<TfListener>,
     _static_frame: String,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 #[derive(Clone)]
@@ -92,7 +92,7 @@ pub fn colorize_points(mut points: Vec<ColoredPoint>, min_z: f64, max_z: f64) ->
 impl PointCloud2Listener {
     pub fn new(
         config: PointCloud2ListenerConfig,
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         static_frame: String,
     ) -> PointCloud2Listener {
         let occ_points = Arc::new(RwLock::new(Vec::<ColoredPoint>::new()));
@@ -100,7 +100,7 @@ impl PointCloud2Listener {
         let str_ = static_frame.clone();
         let local_listener = tf_listener.clone();
         let use_rgb = config.use_rgb.clone();
-        let _sub = rosrust::subscribe(
+        let _sub = source::subscribe(
             &config.topic,
             1,
             move |cloud: rosrust_msg::sensor_msgs::PointCloud2| {
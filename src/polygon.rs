@@ -1,8 +1,9 @@
+use crate::source::{self, Subscription};
+use crate::tf::TfListener;
 use crate::{
     config::ListenerConfigColor, transformation::ros_transform_to_isometry,
 };
 use nalgebra::Point3;
-use rustros_tf;
 use std::sync::{Arc, RwLock};
 use tui::widgets::canvas::Line;
 use tui::style::Color;
@@ -20,13 +21,13 @@ pub struct PolygonData {
     pub polygon_stamped_msg: Option<rosrust_msg::geometry_msgs::PolygonStamped>,
     pub lines_in_static_frame: Option<Vec<Line>>,
     _color: Color,
-    _tf_listener: Arc<rustros_tf::TfListener>,
+    _tf_listener: Arc<TfListener>,
     _static_frame: String,
 }
 
 pub struct PolygonListener {
     _data: Arc<RwLock<PolygonData>>,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl PolygonData {
@@ -75,7 +76,7 @@ impl PolygonData {
 impl PolygonListener {
     pub fn new(
         config: ListenerConfigColor,
-        tf_listener: Arc<rustros_tf::TfListener>,
+        tf_listener: Arc<TfListener>,
         static_frame: String,
     ) -> PolygonListener {
         let data = Arc::new(RwLock::new(PolygonData {
@@ -87,7 +88,7 @@ impl PolygonListener {
         }));
 
         let cloned_data = data.clone();
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             1,
             move |msg: rosrust_msg::geometry_msgs::PolygonStamped| {
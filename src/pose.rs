@@ -1,4 +1,5 @@
 use crate::config::{Color, PoseListenerConfig};
+use crate::source::{self, Subscription};
 use crate::transformation::ros_pose_to_isometry;
 use nalgebra::geometry::{Isometry3, Point3};
 use std::option::Option;
@@ -6,8 +7,6 @@ use std::sync::{Arc, RwLock};
 use tui::style;
 use tui::widgets::canvas::Line;
 
-use rosrust;
-
 fn pose_to_arrow(pose: &Isometry3<f64>, length: f64, color: &Color) -> Vec<Line> {
     let mut lines: Vec<Line> = Vec::new();
     let tui_color = style::Color::Rgb(color.r, color.g, color.b);
@@ -89,14 +88,14 @@ fn poses_to_lines(poses: &Vec<Isometry3<f64>>, color: &Color) -> Vec<Line> {
 pub struct PoseStampedListener {
     config: PoseListenerConfig,
     pose: Arc<RwLock<Option<Isometry3<f64>>>>,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl PoseStampedListener {
     pub fn new(config: PoseListenerConfig) -> PoseStampedListener {
         let pose = Arc::new(RwLock::new(None));
         let cb_pose = pose.clone();
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             2,
             move |pose_msg: rosrust_msg::geometry_msgs::PoseStamped| {
@@ -128,14 +127,14 @@ impl PoseStampedListener {
 pub struct PoseArrayListener {
     config: PoseListenerConfig,
     poses: Arc<RwLock<Vec<Isometry3<f64>>>>,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl PoseArrayListener {
     pub fn new(config: PoseListenerConfig) -> PoseArrayListener {
         let poses = Arc::new(RwLock::new(Vec::<Isometry3<f64>>::new()));
         let cb_poses = poses.clone();
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             2,
             move |pose_array: rosrust_msg::geometry_msgs::PoseArray| {
@@ -189,14 +188,14 @@ impl PoseArrayListener {
 pub struct PathListener {
     config: PoseListenerConfig,
     poses: Arc<RwLock<Vec<Isometry3<f64>>>>,
-    _subscriber: rosrust::Subscriber,
+    _subscriber: Subscription,
 }
 
 impl PathListener {
     pub fn new(config: PoseListenerConfig) -> PathListener {
         let poses = Arc::new(RwLock::new(Vec::<Isometry3<f64>>::new()));
         let cb_poses = poses.clone();
-        let sub = rosrust::subscribe(
+        let sub = source::subscribe(
             &config.topic,
             2,
             move |path: rosrust_msg::nav_msgs::Path| {
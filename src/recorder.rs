@@ -0,0 +1,115 @@
+//! Records topics to a bag, with the messages as sent by their publishers.
+use crate::bag::{to_nanos, BagWriter, Connection};
+use rosrust::RawMessage;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Topics always recorded, to be able to show the other ones.
+const TF_TOPICS: [&str; 2] = ["/tf", "/tf_static"];
+
+pub struct Recorder {
+    pub path: String,
+    writer: Arc<Mutex<Option<BagWriter>>>,
+    error: Arc<Mutex<Option<String>>>,
+    _subscribers: Vec<rosrust::Subscriber>,
+}
+
+impl Recorder {
+    /// Starts recording the topics, and TF, to a new bag.
+    pub fn start(path: &str, topics: &[String]) -> Result<Recorder, String> {
+        let writer = BagWriter::create(path).map_err(|e| e.to_string())?;
+        let writer = Arc::new(Mutex::new(Some(writer)));
+        let error = Arc::new(Mutex::new(None));
+        let mut resolved: Vec<String> = TF_TOPICS.iter().map(|t| t.to_string()).collect();
+        for topic in topics {
+            // Relative topics are resolved in the global namespace, like in termviz
+            let topic = format!("/{}", topic.trim_start_matches('/'));
+            if !resolved.contains(&topic) {
+                resolved.push(topic);
+            }
+        }
+
+        let mut subscribers = Vec::new();
+        for topic in resolved {
+            // Only the first connection is kept, all the publishers of a topic have the same type
+            let conn = Arc::new(Mutex::new(None));
+            let cb_conn = conn.clone();
+            let cb_writer = writer.clone();
+            let cb_error = error.clone();
+            let header_writer = writer.clone();
+            let header_topic = topic.clone();
+            let subscriber = rosrust::subscribe_with_ids_and_headers(
+                &topic,
+                100,
+                move |msg: RawMessage, _caller_id: &str| {
+                    let conn = match *cb_conn.lock().unwrap() {
+                        Some(conn) => conn,
+                        None => return,
+                    };
+                    if let Some(writer) = cb_writer.lock().unwrap().as_mut() {
+                        let time = to_nanos(&rosrust::now());
+                        if let Err(e) = writer.write_message(conn, time, &msg.0) {
+                            *cb_error.lock().unwrap() = Some(e.to_string());
+                        }
+                    }
+                },
+                move |headers: HashMap<String, String>| {
+                    let mut conn = conn.lock().unwrap();
+                    if conn.is_some() {
+                        return;
+                    }
+                    let get = |name: &str| headers.get(name).cloned().unwrap_or_default();
+                    if let Some(writer) = header_writer.lock().unwrap().as_mut() {
+                        *conn = Some(writer.add_connection(Connection {
+                            topic: header_topic.clone(),
+                            msg_type: get("type"),
+                            md5sum: get("md5sum"),
+                            definition: get("message_definition"),
+                        }));
+                    }
+                },
+            )
+            .map_err(|e| e.to_string())?;
+            subscribers.push(subscriber);
+        }
+
+        Ok(Recorder {
+            path: path.to_string(),
+            writer,
+            error,
+            _subscribers: subscribers,
+        })
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.writer
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |writer| writer.message_count)
+    }
+
+    /// Returns the last error while writing, if any.
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Stops recording and writes the index of the bag.
+    pub fn stop(self) -> Result<(), String> {
+        self.close()
+    }
+
+    fn close(&self) -> Result<(), String> {
+        match self.writer.lock().unwrap().take() {
+            Some(writer) => writer.close().map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    /// Closes the bag if the recording was not stopped, e.g., when quitting termviz.
+    fn drop(&mut self) {
+        self.close().ok();
+    }
+}
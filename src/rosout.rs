@@ -0,0 +1,114 @@
+//! Listener of the log messages aggregated by rosout.
+use crate::config::RosoutConfig;
+use crate::source::{self, Subscription};
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+use rosrust;
+use rosrust_msg::rosgraph_msgs::Log;
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [
+        Severity::Debug,
+        Severity::Info,
+        Severity::Warn,
+        Severity::Error,
+        Severity::Fatal,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Debug => "DEBUG",
+            Severity::Info => "INFO",
+            Severity::Warn => "WARN",
+            Severity::Error => "ERROR",
+            Severity::Fatal => "FATAL",
+        }
+    }
+}
+
+fn severity(msg: &Log) -> Severity {
+    match msg.level {
+        Log::DEBUG => Severity::Debug,
+        Log::WARN => Severity::Warn,
+        Log::ERROR => Severity::Error,
+        Log::FATAL => Severity::Fatal,
+        _ => Severity::Info,
+    }
+}
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub stamp: rosrust::Time,
+    pub severity: Severity,
+    pub node: String,
+    pub msg: String,
+}
+
+/// Number of warnings and errors (including fatal ones) received since the last reset.
+#[derive(Clone, Copy, Default)]
+pub struct LogCounts {
+    pub warnings: usize,
+    pub errors: usize,
+}
+
+pub struct LogListener {
+    pub entries: Arc<RwLock<VecDeque<LogEntry>>>,
+    pub counts: Arc<RwLock<LogCounts>>,
+    _subscriber: Option<Subscription>,
+}
+
+impl LogListener {
+    pub fn new(config: &RosoutConfig) -> LogListener {
+        let entries = Arc::new(RwLock::new(VecDeque::new()));
+        let counts = Arc::new(RwLock::new(LogCounts::default()));
+        let cb_entries = entries.clone();
+        let cb_counts = counts.clone();
+        let max_entries = config.max_entries;
+        let subscriber = source::subscribe(&config.topic, 100, move |msg: Log| {
+            let entry = LogEntry {
+                stamp: msg.header.stamp,
+                severity: severity(&msg),
+                node: msg.name,
+                msg: msg.msg,
+            };
+            {
+                let mut counts = cb_counts.write().unwrap();
+                match entry.severity {
+                    Severity::Warn => counts.warnings += 1,
+                    Severity::Error | Severity::Fatal => counts.errors += 1,
+                    _ => (),
+                }
+            }
+            let mut entries = cb_entries.write().unwrap();
+            entries.push_back(entry);
+            while entries.len() > max_entries {
+                entries.pop_front();
+            }
+        })
+        .ok();
+
+        LogListener {
+            entries,
+            counts,
+            _subscriber: subscriber,
+        }
+    }
+
+    pub fn counts(&self) -> LogCounts {
+        *self.counts.read().unwrap()
+    }
+
+    pub fn reset_counts(&self) {
+        *self.counts.write().unwrap() = LogCounts::default();
+    }
+}
@@ -0,0 +1,358 @@
+//! Minimal client for services of any type.
+//!
+//! rosrust only calls services whose type is known at compile time. This module finds the type
+//! of a service by probing it, reads its definition from the `.srv` file of the package, and
+//! talks TCPROS directly, encoding and decoding the messages at runtime.
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rosrust;
+use rosrust::{DynamicMsg, MsgValue};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const BUILTIN_TYPES: [&str; 16] = [
+    "bool", "int8", "uint8", "int16", "uint16", "int32", "uint32", "int64", "uint64", "float32",
+    "float64", "string", "time", "duration", "byte", "char",
+];
+const DEFINITION_SEPARATOR: &str =
+    "================================================================================\n";
+
+/// Returns the names of the services currently advertised.
+pub fn list_services() -> Result<Vec<String>, String> {
+    let state = rosrust::state().map_err(|e| e.to_string())?;
+    let mut services: Vec<String> = state.services.into_iter().map(|s| s.name).collect();
+    services.sort();
+    Ok(services)
+}
+
+/// Directories that may contain ROS packages.
+fn package_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(paths) = env::var("ROS_PACKAGE_PATH") {
+        roots.extend(env::split_paths(&paths));
+    }
+    if let Ok(paths) = env::var("CMAKE_PREFIX_PATH") {
+        roots.extend(env::split_paths(&paths).map(|path| path.join("share")));
+    }
+    roots
+}
+
+/// Reads the `.msg` or `.srv` file of a type, e.g., `std_srvs/SetBool`.
+fn read_definition(full_type: &str, kind: &str) -> Result<String, String> {
+    let (package, name) = full_type
+        .split_once('/')
+        .ok_or_else(|| format!("invalid type {}", full_type))?;
+    let file = format!("{}.{}", name, kind);
+    for root in package_roots() {
+        let mut candidates = vec![root.join(package).join(kind).join(&file)];
+        if root.ends_with(package) {
+            candidates.push(root.join(kind).join(&file));
+        }
+        for candidate in candidates {
+            if let Ok(definition) = fs::read_to_string(&candidate) {
+                return Ok(definition);
+            }
+        }
+    }
+    Err(format!("definition of {} not found", full_type))
+}
+
+/// Returns the (type, name) of the fields of a message definition, without the constants.
+fn parse_fields(definition: &str) -> Vec<(String, String)> {
+    definition
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty() && !line.contains('='))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            match (tokens.next(), tokens.next()) {
+                (Some(field_type), Some(name)) => Some((field_type.to_string(), name.to_string())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the full name of the message type of a field, if it is not a builtin type.
+fn dependency(field_type: &str, package: &str) -> Option<String> {
+    let base = field_type.split('[').next().unwrap_or(field_type);
+    if base == "Header" {
+        return Some("std_msgs/Header".to_string());
+    }
+    if BUILTIN_TYPES.contains(&base) {
+        return None;
+    }
+    if base.contains('/') {
+        Some(base.to_string())
+    } else {
+        Some(format!("{}/{}", package, base))
+    }
+}
+
+/// Builds a message definition including the definitions of its dependencies, in the format
+/// sent by publishers in the connection headers.
+fn full_definition(body: &str, package: &str) -> Result<String, String> {
+    let mut definition = body.to_string();
+    let mut to_visit: VecDeque<(String, String)> = VecDeque::new();
+    let mut visited = HashSet::new();
+    to_visit.push_back((body.to_string(), package.to_string()));
+    while let Some((body, package)) = to_visit.pop_front() {
+        for (field_type, _) in parse_fields(&body) {
+            let dependency = match dependency(&field_type, &package) {
+                Some(dependency) => dependency,
+                None => continue,
+            };
+            if !visited.insert(dependency.clone()) {
+                continue;
+            }
+            let dependency_body = read_definition(&dependency, "msg")?;
+            definition += &format!(
+                "\n{}MSG: {}\n{}",
+                DEFINITION_SEPARATOR, dependency, dependency_body
+            );
+            let dependency_package = dependency.split('/').next().unwrap_or("").to_string();
+            to_visit.push_back((dependency_body, dependency_package));
+        }
+    }
+    Ok(definition)
+}
+
+pub struct ServiceDefinition {
+    pub srv_type: String,
+    pub request_fields: Vec<(String, String)>,
+    request: DynamicMsg,
+    response: DynamicMsg,
+}
+
+impl ServiceDefinition {
+    /// Loads the definition of a service type, e.g., `std_srvs/SetBool`.
+    pub fn load(srv_type: &str) -> Result<ServiceDefinition, String> {
+        let package = srv_type.split('/').next().unwrap_or("");
+        let definition = read_definition(srv_type, "srv")?;
+        // The request and the response are separated by a line containing only "---"
+        let lines: Vec<&str> = definition.lines().collect();
+        let separator = lines
+            .iter()
+            .position(|line| line.trim() == "---")
+            .ok_or_else(|| format!("invalid definition of {}", srv_type))?;
+        let request_body = lines[..separator].join("\n");
+        let response_body = lines[separator + 1..].join("\n");
+        let request = DynamicMsg::new(
+            &format!("{}Request", srv_type),
+            &full_definition(&request_body, package)?,
+        )
+        .map_err(|e| e.to_string())?;
+        let response = DynamicMsg::new(
+            &format!("{}Response", srv_type),
+            &full_definition(&response_body, package)?,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(ServiceDefinition {
+            srv_type: srv_type.to_string(),
+            request_fields: parse_fields(&request_body),
+            request,
+            response,
+        })
+    }
+}
+
+/// Parses the value of a request field typed by the user. Only scalar fields are supported.
+pub fn parse_field(field_type: &str, text: &str) -> Result<MsgValue, String> {
+    let invalid = || format!("'{}' is not a valid {}", text, field_type);
+    let text = text.trim();
+    match field_type {
+        "bool" => match text.to_lowercase().as_str() {
+            "true" | "1" => Ok(MsgValue::Bool(true)),
+            "false" | "0" => Ok(MsgValue::Bool(false)),
+            _ => Err(invalid()),
+        },
+        "int8" | "byte" => text.parse().map(MsgValue::I8).map_err(|_| invalid()),
+        "uint8" | "char" => text.parse().map(MsgValue::U8).map_err(|_| invalid()),
+        "int16" => text.parse().map(MsgValue::I16).map_err(|_| invalid()),
+        "uint16" => text.parse().map(MsgValue::U16).map_err(|_| invalid()),
+        "int32" => text.parse().map(MsgValue::I32).map_err(|_| invalid()),
+        "uint32" => text.parse().map(MsgValue::U32).map_err(|_| invalid()),
+        "int64" => text.parse().map(MsgValue::I64).map_err(|_| invalid()),
+        "uint64" => text.parse().map(MsgValue::U64).map_err(|_| invalid()),
+        "float32" => text.parse().map(MsgValue::F32).map_err(|_| invalid()),
+        "float64" => text.parse().map(MsgValue::F64).map_err(|_| invalid()),
+        "string" => Ok(MsgValue::String(text.to_string())),
+        _ => Err(format!("fields of type {} are not supported", field_type)),
+    }
+}
+
+/// Returns the host and port of the URI of the ROS master.
+fn master_address() -> String {
+    let uri = env::var("ROS_MASTER_URI").unwrap_or_else(|_| "http://localhost:11311".to_string());
+    uri.trim_start_matches("http://")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Opens a connection to a `host:port` address, giving up after the timeout.
+fn open_stream(address: &str) -> Result<TcpStream, String> {
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {}", address, e))?;
+    let mut error = format!("{}: no address found", address);
+    for socket_address in addresses {
+        match TcpStream::connect_timeout(&socket_address, TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(TIMEOUT)).ok();
+                stream.set_write_timeout(Some(TIMEOUT)).ok();
+                return Ok(stream);
+            }
+            Err(e) => error = format!("{}: {}", address, e),
+        }
+    }
+    Err(error)
+}
+
+/// Escapes the characters that have a meaning in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Asks the master for the address of the node providing a service.
+fn lookup_service(service: &str) -> Result<String, String> {
+    let master = master_address();
+    let body = format!(
+        "<?xml version=\"1.0\"?><methodCall><methodName>lookupService</methodName><params>\
+         <param><value><string>{}</string></value></param>\
+         <param><value><string>{}</string></value></param>\
+         </params></methodCall>",
+        escape_xml(&rosrust::name()),
+        escape_xml(service)
+    );
+    let request = format!(
+        "POST / HTTP/1.0\r\nHost: {}\r\nContent-Type: text/xml\r\nContent-Length: {}\r\n\r\n{}",
+        master,
+        body.len(),
+        body
+    );
+    let mut stream = open_stream(&master)?;
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| e.to_string())?;
+    if response.contains("<fault>") {
+        return Err(format!("the master returned a fault for {}", service));
+    }
+    let start = response
+        .find("rosrpc://")
+        .ok_or_else(|| format!("service {} not found", service))?
+        + "rosrpc://".len();
+    let address = &response[start..];
+    let end = address
+        .find(|c| c == '<' || c == '/')
+        .unwrap_or(address.len());
+    Ok(address[..end].to_string())
+}
+
+fn write_header(stream: &mut TcpStream, fields: &[(&str, &str)]) -> std::io::Result<()> {
+    let mut header = Vec::new();
+    for (key, value) in fields {
+        let field = format!("{}={}", key, value);
+        header.write_u32::<LittleEndian>(field.len() as u32)?;
+        header.write_all(field.as_bytes())?;
+    }
+    stream.write_u32::<LittleEndian>(header.len() as u32)?;
+    stream.write_all(&header)
+}
+
+fn read_block(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let len = stream.read_u32::<LittleEndian>()?;
+    let mut block = vec![0; len as usize];
+    stream.read_exact(&mut block)?;
+    Ok(block)
+}
+
+fn read_header(stream: &mut TcpStream) -> std::io::Result<HashMap<String, String>> {
+    let header = read_block(stream)?;
+    let mut fields = HashMap::new();
+    let mut reader = &header[..];
+    while !reader.is_empty() {
+        let len = reader.read_u32::<LittleEndian>()? as usize;
+        if len > reader.len() {
+            break;
+        }
+        let field = String::from_utf8_lossy(&reader[..len]).to_string();
+        reader = &reader[len..];
+        if let Some((key, value)) = field.split_once('=') {
+            fields.insert(key.to_string(), value.to_string());
+        }
+    }
+    Ok(fields)
+}
+
+/// Connects to a service and exchanges the connection headers.
+fn connect(service: &str, probe: bool) -> Result<(TcpStream, HashMap<String, String>), String> {
+    let address = lookup_service(service)?;
+    let mut stream = open_stream(&address)?;
+    let caller_id = rosrust::name();
+    let mut fields = vec![
+        ("callerid", caller_id.as_str()),
+        ("service", service),
+        ("md5sum", "*"),
+        ("persistent", "0"),
+    ];
+    if probe {
+        fields.push(("probe", "1"));
+    }
+    write_header(&mut stream, &fields).map_err(|e| e.to_string())?;
+    let header = read_header(&mut stream).map_err(|e| e.to_string())?;
+    if let Some(error) = header.get("error") {
+        return Err(error.clone());
+    }
+    Ok((stream, header))
+}
+
+/// Returns the type of a service, e.g., `std_srvs/Trigger`.
+pub fn service_type(service: &str) -> Result<String, String> {
+    let (_, header) = connect(service, true)?;
+    header
+        .get("type")
+        .cloned()
+        .ok_or_else(|| format!("{} did not send its type", service))
+}
+
+/// Calls a service and returns the response.
+pub fn call(
+    service: &str,
+    definition: &ServiceDefinition,
+    request: &HashMap<String, MsgValue>,
+) -> Result<MsgValue, String> {
+    let mut body = Vec::new();
+    definition
+        .request
+        .encode(request, &mut body)
+        .map_err(|e| e.to_string())?;
+    let (mut stream, _) = connect(service, false)?;
+    stream
+        .write_u32::<LittleEndian>(body.len() as u32)
+        .and_then(|_| stream.write_all(&body))
+        .map_err(|e| e.to_string())?;
+    let ok = stream.read_u8().map_err(|e| e.to_string())?;
+    let response = read_block(&mut stream).map_err(|e| e.to_string())?;
+    if ok == 0 {
+        return Err(String::from_utf8_lossy(&response).to_string());
+    }
+    definition
+        .response
+        .decode(&response[..])
+        .map(MsgValue::Message)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,125 @@
+//! Source of the messages received by the listeners.
+//!
+//! The listeners normally subscribe to ROS. When termviz runs offline, e.g., to play back a
+//! bag, no ROS master is available and the subscriptions are instead registered here, to be
+//! fed with the serialized messages through [`dispatch`].
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Called with the md5sum of the message type and the serialized message.
+type Handler = Arc<dyn Fn(&str, &[u8]) + Send + Sync>;
+type ResetHandler = Arc<dyn Fn() + Send + Sync>;
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static HANDLERS: Mutex<Vec<(usize, String, Handler)>> = Mutex::new(Vec::new());
+static RESET_HANDLERS: Mutex<Vec<(usize, ResetHandler)>> = Mutex::new(Vec::new());
+
+/// Makes the following subscriptions offline. Must be called before creating any listener.
+pub fn set_offline() {
+    OFFLINE.store(true, Ordering::SeqCst);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+/// Offline topics are always global, as there is no node namespace.
+fn resolve(topic: &str) -> String {
+    format!("/{}", topic.trim_start_matches('/'))
+}
+
+/// A subscription, which stops when dropped.
+pub enum Subscription {
+    Ros(rosrust::Subscriber),
+    Offline(usize),
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Subscription::Offline(id) = self {
+            HANDLERS.lock().unwrap().retain(|(i, _, _)| i != id);
+            RESET_HANDLERS.lock().unwrap().retain(|(i, _)| i != id);
+        }
+    }
+}
+
+/// Subscribes to a topic, like `rosrust::subscribe`.
+pub fn subscribe<T, F>(
+    topic: &str,
+    queue_size: usize,
+    callback: F,
+) -> rosrust::error::Result<Subscription>
+where
+    T: rosrust::Message,
+    F: Fn(T) + Send + 'static,
+{
+    if !is_offline() {
+        return rosrust::subscribe(topic, queue_size, callback).map(Subscription::Ros);
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let callback = Mutex::new(callback);
+    let expected_md5sum = T::md5sum();
+    let handler: Handler = Arc::new(move |md5sum: &str, data: &[u8]| {
+        // Messages that do not match the type of the subscription are skipped, "*" matches any
+        if md5sum != "*" && md5sum != expected_md5sum {
+            return;
+        }
+        if let Ok(msg) = T::decode_slice(data) {
+            (callback.lock().unwrap())(msg);
+        }
+    });
+    HANDLERS.lock().unwrap().push((id, resolve(topic), handler));
+    Ok(Subscription::Offline(id))
+}
+
+/// Registers a function clearing the state built from the messages of an offline subscription,
+/// called by [`reset`]. It is removed with the subscription.
+pub fn on_reset<F>(subscription: &Subscription, handler: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    if let Subscription::Offline(id) = subscription {
+        RESET_HANDLERS
+            .lock()
+            .unwrap()
+            .push((*id, Arc::new(handler)));
+    }
+}
+
+/// Clears the state of the offline listeners, e.g., before playing back from an earlier time.
+pub fn reset() {
+    let handlers: Vec<ResetHandler> = RESET_HANDLERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, handler)| handler.clone())
+        .collect();
+    for handler in handlers {
+        handler();
+    }
+}
+
+/// Passes a serialized message to the offline subscriptions of a topic, if its type has the
+/// given md5sum.
+pub fn dispatch(topic: &str, md5sum: &str, data: &[u8]) {
+    let topic = resolve(topic);
+    // The handlers are called without holding the lock, as they may subscribe themselves
+    let handlers: Vec<Handler> = HANDLERS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, t, _)| *t == topic)
+        .map(|(_, _, handler)| handler.clone())
+        .collect();
+    for handler in handlers {
+        handler(md5sum, data);
+    }
+}
+
+/// Passes a message to the offline subscriptions of a topic.
+pub fn dispatch_message<T: rosrust::Message>(topic: &str, msg: &T) {
+    if let Ok(data) = msg.encode_vec() {
+        dispatch(topic, &T::md5sum(), &data);
+    }
+}
@@ -0,0 +1,115 @@
+//! TF listener, listening either to ROS or, when offline, to the transforms dispatched by the
+//! message source.
+use crate::source::{self, Subscription};
+use crate::transformation::{iso3d_to_ros, ros_transform_to_isometry};
+use nalgebra::Isometry3;
+use rosrust_msg::geometry_msgs::TransformStamped;
+use rosrust_msg::tf2_msgs::TFMessage;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use rosrust;
+use rustros_tf;
+
+/// Maximum depth of the TF tree, to stop on loops.
+const MAX_DEPTH: usize = 100;
+
+pub enum TfListener {
+    Ros(rustros_tf::TfListener),
+    Offline(OfflineTfListener),
+}
+
+impl TfListener {
+    pub fn new() -> TfListener {
+        if source::is_offline() {
+            TfListener::Offline(OfflineTfListener::new())
+        } else {
+            TfListener::Ros(rustros_tf::TfListener::new())
+        }
+    }
+
+    /// Returns the transform of the `to` frame in the `from` frame.
+    pub fn lookup_transform(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+    ) -> Result<TransformStamped, String> {
+        match self {
+            TfListener::Ros(listener) => listener
+                .lookup_transform(from, to, time)
+                .map_err(|e| format!("{:?}", e)),
+            TfListener::Offline(listener) => listener.lookup_transform(from, to),
+        }
+    }
+}
+
+/// Keeps the latest transform of each frame, without interpolation in time.
+pub struct OfflineTfListener {
+    transforms: Arc<RwLock<HashMap<String, TransformStamped>>>,
+    _subscribers: Vec<Subscription>,
+}
+
+impl OfflineTfListener {
+    fn new() -> OfflineTfListener {
+        let transforms = Arc::new(RwLock::new(HashMap::new()));
+        let subscribers: Vec<Subscription> = ["/tf", "/tf_static"]
+            .iter()
+            .filter_map(|topic| {
+                let cb_transforms = transforms.clone();
+                source::subscribe(topic, 100, move |msg: TFMessage| {
+                    let mut transforms = cb_transforms.write().unwrap();
+                    for tf in msg.transforms {
+                        let frame = tf.child_frame_id.trim_start_matches('/').to_string();
+                        transforms.insert(frame, tf);
+                    }
+                })
+                .ok()
+            })
+            .collect();
+        // The transforms are received again after a seek, frames may not exist yet at that time
+        if let Some(subscriber) = subscribers.first() {
+            let reset_transforms = transforms.clone();
+            source::on_reset(subscriber, move || {
+                reset_transforms.write().unwrap().clear()
+            });
+        }
+        OfflineTfListener {
+            transforms,
+            _subscribers: subscribers,
+        }
+    }
+
+    fn lookup_transform(&self, from: &str, to: &str) -> Result<TransformStamped, String> {
+        let transforms = self.transforms.read().unwrap();
+        let (from_root, from_pose) = root_pose(&transforms, from);
+        let (to_root, to_pose) = root_pose(&transforms, to);
+        if from_root != to_root {
+            return Err(format!("no transform from {} to {}", to, from));
+        }
+        let mut tf = TransformStamped::default();
+        tf.header.frame_id = from.to_string();
+        tf.child_frame_id = to.to_string();
+        tf.transform = iso3d_to_ros(&(from_pose.inverse() * to_pose));
+        Ok(tf)
+    }
+}
+
+/// Returns the root of the tree of a frame and the pose of the frame in it.
+fn root_pose(
+    transforms: &HashMap<String, TransformStamped>,
+    frame: &str,
+) -> (String, Isometry3<f64>) {
+    let mut frame = frame.trim_start_matches('/');
+    let mut pose = Isometry3::identity();
+    for _ in 0..MAX_DEPTH {
+        match transforms.get(frame) {
+            Some(tf) => {
+                pose = ros_transform_to_isometry(&tf.transform) * pose;
+                frame = tf.header.frame_id.trim_start_matches('/');
+            }
+            None => break,
+        }
+    }
+    (frame.to_string(), pose)
+}
@@ -66,3 +66,54 @@ pub fn ros_transform_to_isometry(tf: &rosrust_msg::geometry_msgs::Transform) ->
     ));
     Isometry3::from_parts(tra, rot)
 }
+
+pub fn iso3d_to_ros(iso: &Isometry3<f64>) -> rosrust_msg::geometry_msgs::Transform {
+    let rot = iso.rotation.quaternion();
+    rosrust_msg::geometry_msgs::Transform {
+        translation: rosrust_msg::geometry_msgs::Vector3 {
+            x: iso.translation.x,
+            y: iso.translation.y,
+            z: iso.translation.z,
+        },
+        rotation: rosrust_msg::geometry_msgs::Quaternion {
+            x: rot[0],
+            y: rot[1],
+            z: rot[2],
+            w: rot[3],
+        },
+    }
+}
+
+pub fn iso3d_to_iso2d(iso: &Isometry3<f64>) -> Isometry2<f64> {
+    let (_roll, _pitch, yaw) = iso.rotation.euler_angles();
+    Isometry2::new(Vector2::new(iso.translation.x, iso.translation.y), yaw)
+}
+
+pub fn iso2d_to_ros_pose(iso: &Isometry2<f64>) -> rosrust_msg::geometry_msgs::Pose {
+    let tf = iso2d_to_ros(iso);
+    rosrust_msg::geometry_msgs::Pose {
+        position: rosrust_msg::geometry_msgs::Point {
+            x: tf.translation.x,
+            y: tf.translation.y,
+            z: tf.translation.z,
+        },
+        orientation: tf.rotation,
+    }
+}
+
+pub fn iso3d_to_ros_pose(iso: &Isometry3<f64>) -> rosrust_msg::geometry_msgs::Pose {
+    let rot = iso.rotation.quaternion();
+    rosrust_msg::geometry_msgs::Pose {
+        position: rosrust_msg::geometry_msgs::Point {
+            x: iso.translation.x,
+            y: iso.translation.y,
+            z: iso.translation.z,
+        },
+        orientation: rosrust_msg::geometry_msgs::Quaternion {
+            x: rot[0],
+            y: rot[1],
+            z: rot[2],
+            w: rot[3],
+        },
+    }
+}